@@ -0,0 +1,43 @@
+//! Author: TheLazyFerret (https://github.com/TheLazyFerret)
+//! Copyright (c) 2025 TheLazyFerret
+//! Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//!
+//! Measures the instruction dispatch of the general step loop on a hot loop with a branch.
+//! Run with `cargo bench`.
+
+use std::{
+  hint::black_box,
+  time::{Duration, Instant},
+};
+
+use interpreter_rs::simulator::Simulator;
+
+const ROUNDS: i32 = 1_000_000;
+const ITERATIONS: u32 = 10;
+
+/// Returns a loop of arithmetic instructions closed by a conditional branch.
+fn program() -> Vec<String> {
+  format!("@MAIN\nLI $2 {ROUNDS}\n@LOOP\nADDI $1 $1 1\nXOR $3 $1 $2\nADD $4 $4 $3\nBLT $1 $2 @LOOP")
+    .lines()
+    .map(String::from)
+    .collect()
+}
+
+fn main() {
+  let mut sim = Simulator::new();
+  sim.load(&program()).unwrap();
+  let mut steps = 0;
+  let start = Instant::now();
+  for _ in 0..ITERATIONS {
+    sim.reset();
+    black_box(sim.run(false)).unwrap();
+    steps += sim.steps();
+  }
+  let elapsed: Duration = start.elapsed();
+  println!("{} steps in a hot loop", steps / u64::from(ITERATIONS));
+  println!("  per run:  {:?}", elapsed / ITERATIONS);
+  println!(
+    "  per step: {:.2} ns",
+    elapsed.as_nanos() as f64 / steps as f64
+  );
+}
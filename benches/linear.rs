@@ -0,0 +1,54 @@
+//! Author: TheLazyFerret (https://github.com/TheLazyFerret)
+//! Copyright (c) 2025 TheLazyFerret
+//! Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//!
+//! Compares the fast path for programs without jumps with the general step loop.
+//! Run with `cargo bench`.
+
+use std::{
+  hint::black_box,
+  time::{Duration, Instant},
+};
+
+use interpreter_rs::simulator::{ErrorPolicy, Simulator};
+
+const INSTRUCTIONS: usize = 100_000;
+const ITERATIONS: u32 = 20;
+
+/// Returns a program of arithmetic instructions, without any jump.
+fn program() -> Vec<String> {
+  let mut lines = vec![String::from("@MAIN"), String::from("LI $2 3")];
+  for n in 0..INSTRUCTIONS {
+    lines.push(match n % 3 {
+      0 => String::from("ADD $1 $1 $2"),
+      1 => String::from("XOR $3 $1 $2"),
+      _ => String::from("SUB $1 $1 $3"),
+    });
+  }
+  lines
+}
+
+/// Returns the mean time to run the program, loaded once with the given policy.
+fn measure(lines: &[String], policy: ErrorPolicy) -> Duration {
+  let mut sim = Simulator::new().with_error_policy(policy);
+  sim.load(lines).unwrap();
+  let start = Instant::now();
+  for _ in 0..ITERATIONS {
+    black_box(sim.run(false)).unwrap();
+  }
+  start.elapsed() / ITERATIONS
+}
+
+fn main() {
+  let lines = program();
+  // Only `ErrorPolicy::Abort` takes the fast path; `Continue` always goes through `step`.
+  let fast = measure(&lines, ErrorPolicy::Abort);
+  let general = measure(&lines, ErrorPolicy::Continue);
+  println!("{INSTRUCTIONS} instructions without jumps");
+  println!("  fast path: {fast:?}");
+  println!("  step loop: {general:?}");
+  println!(
+    "  speedup:   {:.2}x",
+    general.as_secs_f64() / fast.as_secs_f64()
+  );
+}
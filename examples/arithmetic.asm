@@ -0,0 +1,19 @@
+// Integer arithmetic, including the wrapping ones
+@MAIN
+LI $1 17
+LI $2 5
+ADD $3 $1 $2
+PRINT $3
+SUB $3 $2 $1
+PRINT $3
+MUL $3 $1 $2
+PRINT $3
+DIV $3 $1 $2
+PRINT $3
+REM $3 $1 $2
+PRINT $3
+LI $4 2147483647
+ADDI $4 $4 1
+PRINT $4
+POWMOD $3 $2 $1 $1
+PRINT $3
@@ -0,0 +1,16 @@
+// Prints the largest of two numbers, and whether they are equal
+@MAIN
+LI $1 -4
+LI $2 7
+BGT $1 $2 @FIRST
+  PRINT $2
+  JUMP @COMPARE
+@FIRST
+  PRINT $1
+@COMPARE
+SETEQ $3 $1 $2
+PRINT $3
+BNE $1 $2 @END
+  PRINT $1
+@END
+EXIT
@@ -0,0 +1,11 @@
+// Sum of the numbers from 1 to 10, printing the partial sums
+@MAIN
+LI $1 1
+LI $2 10
+@LOOP
+  ADD $3 $3 $1
+  PRINTNN $3
+  ADDI $1 $1 1
+  BLE $1 $2 @LOOP
+PRINTLN
+PRINT $3
@@ -0,0 +1,7 @@
+//! Author: TheLazyFerret (https://github.com/TheLazyFerret)
+//! Copyright (c) 2025 TheLazyFerret
+//! Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//!
+//! Library file, shared by the binary and the integration tests
+
+pub mod simulator;
@@ -4,21 +4,21 @@
 //!
 //! Main file
 
-pub mod simulator;
+use std::{env, fs::File};
 
-use std::{env, fs::read_to_string};
-
-use crate::simulator::{Error, Simulator};
+use interpreter_rs::simulator::{Error, Simulator};
 
+/// Usage: `interpreter-rs [--debug] <file>`. With `--debug`, each instruction is written to stderr before running it.
 fn main() -> Result<(), Error> {
-  let args: Vec<String> = env::args().collect();
-  let mut sim = Simulator::new();
-  let lines: Vec<String> = read_to_string(&args[1])
-    .expect("error reading files")
-    .lines()
-    .map(|x| x.to_string())
-    .collect();
-  sim.load(&lines)?;
-  sim.run(false)?;
+  let args: Vec<String> = env::args().skip(1).collect();
+  let debug = args.iter().any(|x| x == "--debug");
+  let path = args
+    .iter()
+    .find(|x| *x != "--debug")
+    .ok_or(Error::ReadFailed)?;
+  let mut sim = Simulator::new().with_verbose(true);
+  let file = File::open(path).map_err(|_| Error::ReadFailed)?;
+  sim.load_reader(file)?;
+  sim.run(debug)?;
   Ok(())
 }
@@ -4,30 +4,152 @@
 //!
 //! Simulator related module
 
+pub mod diagnostic;
+pub mod dot;
+pub mod input;
+#[cfg(feature = "serde")]
+pub mod json;
+pub mod lint;
 pub mod operation;
+pub mod output;
 pub mod parser;
+pub mod program;
 pub mod stack;
 
-use crate::simulator::parser::{preprocess_lines, process_lines};
+use crate::simulator::parser::{
+  check_line_length, collect_expected_output, parse_instruction, preprocess_lines,
+  preserve_comment_lines, process_lines,
+};
 use std::{
-  collections::HashMap,
+  collections::{BTreeMap, HashMap},
   fmt::{self},
+  io::Read,
+  sync::mpsc::Sender,
 };
 
+use input::Input;
+use output::{Output, PrintEvent, ValueFormat};
 use stack::Stack;
 
+/// Number of integer registers of the machine.
+pub const INT_REGISTER_COUNT: usize = 32;
+
+/// Largest address a `.org` directive can move the program to.
+pub const MAX_PROGRAM_SIZE: usize = 1 << 20;
+
+/// Longest source line accepted by default, in bytes.
+pub const DEFAULT_MAX_LINE_LENGTH: usize = 4096;
+
 /// Struct representing the machine.
 #[derive(Debug, Default)]
 pub struct Simulator {
-  int_registers: [i32; 32],
+  int_registers: [i32; INT_REGISTER_COUNT],
   program_counter: usize,
-  labels: HashMap<String, usize>,
+  labels: BTreeMap<String, usize>,
   instructions: Vec<Instructions>,
   stack: Stack<i32>,
+  call_stack: Stack<usize>,
+  error_policy: ErrorPolicy,
+  runtime_warnings: Vec<(usize, Error)>,
+  trace: Option<Vec<TraceEntry>>,
+  output: Output,
+  label_colons: bool,
+  input: Input,
+  overflow: bool,
+  value_format: ValueFormat,
+  stack_balance_check: bool,
+  comments: HashMap<usize, String>,
+  expected_output: Vec<String>,
+  is_linear: bool,
+  register_init: i32,
+  steps: u64,
+  pc_wrap: bool,
+  max_stack_depth: usize,
+  verbose: bool,
+  max_line_length: Option<usize>,
+  require_label_use: bool,
+  exited: bool,
+  max_call_depth: usize,
+  in_slice: bool,
+  pending_separator: bool,
+}
+
+/// Saved machine state, restorable with `Simulator::restore`. The program itself is not saved.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Checkpoint {
+  int_registers: [i32; INT_REGISTER_COUNT],
+  program_counter: usize,
+  stack: Stack<i32>,
+  call_stack: Stack<usize>,
+  overflow: bool,
+  exited: bool,
+}
+
+/// Result of running a bounded number of steps.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StepOutcome {
+  /// Number of instructions actually executed.
+  pub executed: u64,
+  /// Whether the program has ended.
+  pub halted: bool,
+}
+
+/// What changed in one step, from `Simulator::step_diff`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StepDiff {
+  /// Program counter before and after the step.
+  pub pc: (usize, usize),
+  /// Registers that changed, as (register, old value, new value).
+  pub registers: Vec<(usize, i32, i32)>,
+  /// Stack depth before and after the step, if it changed.
+  pub stack_depth: Option<(usize, usize)>,
+}
+
+/// Everything a host gets from `Simulator::execute`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RunResult {
+  /// Text printed by the program.
+  pub output: String,
+  /// Registers when the program ended or failed.
+  pub final_registers: Vec<i32>,
+  /// Number of instructions executed.
+  pub steps: u64,
+  /// Error that stopped the load or the run, if any.
+  pub error: Option<Error>,
+}
+
+/// State of the machine when a run returns.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RunState {
+  /// The program has ended.
+  Halted,
+  /// The program stopped before the end, and can continue stepping.
+  Paused,
+}
+
+/// One executed instruction, recorded while tracing is enabled.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TraceEntry {
+  pub step: usize,
+  pub pc: usize,
+  pub mnemonic: String,
+  pub changed_reg: Option<(usize, i32)>,
+}
+
+/// What to do when an instruction fails during runtime.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ErrorPolicy {
+  /// Stop the execution and return the error (default).
+  #[default]
+  Abort,
+  /// Record the error and keep executing with the next instruction.
+  Continue,
 }
 
 /// Enum representing all the instructions.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "op", content = "args"))]
 pub enum Instructions {
   LI(usize, i32),           // Load imm
   MOVE(usize, usize),       // Move (copy)
@@ -40,15 +162,231 @@ pub enum Instructions {
   EXIT,                     // Exit
   SKIP,                     // Skip the line (no operation)
   LABEL,
-  JUMP(String),              // Jump to a label
-  BEQ(usize, usize, String), // Jump to label if a == b
-  BNE(usize, usize, String), // Jump to label if a != b
-  BLT(usize, usize, String), // Jump to label if a < b
-  BLE(usize, usize, String), // Jump to label if a <= b
-  BGT(usize, usize, String), // Jump to label if a > b
-  BGE(usize, usize, String), // Jump to label if a >= b
+  JUMP(String),               // Jump to a label
+  BEQ(usize, usize, String),  // Jump to label if a == b
+  BNE(usize, usize, String),  // Jump to label if a != b
+  BLT(usize, usize, String),  // Jump to label if a < b
+  BLE(usize, usize, String),  // Jump to label if a <= b
+  BGT(usize, usize, String),  // Jump to label if a > b
+  BGE(usize, usize, String),  // Jump to label if a >= b
+  BLTU(usize, usize, String), // Jump to label if a < b (unsigned)
+  BLEU(usize, usize, String), // Jump to label if a <= b (unsigned)
+  BGTU(usize, usize, String), // Jump to label if a > b (unsigned)
+  BGEU(usize, usize, String), // Jump to label if a >= b (unsigned)
   PUSH(usize),
   POP(usize),
+  SKIPEQ(usize, usize),               // Skip the next instruction if a == b
+  SKIPNE(usize, usize),               // Skip the next instruction if a != b
+  ABSDIFF(usize, usize, usize),       // Absolute difference
+  READALL(usize, usize),              // Read n integers from the input into consecutive registers
+  SP(usize),                          // Load the stack depth
+  VADD(usize, usize, usize, usize),   // Vector addition of n consecutive registers
+  BOV(String),                        // Jump to label if the last arithmetic operation overflowed
+  CLO,                                // Clear the overflow flag
+  PRINTREGS,                          // Print all the registers in one line
+  POWMOD(usize, usize, usize, usize), // Modular exponentiation
+  PRINTNN(usize),                     // Print the value without newline
+  PRINTLN,                            // Print a newline
+  BSWAP(usize, usize),                // Reverse the byte order
+  RBIT(usize, usize),                 // Reverse the bit order
+  SETEQ(usize, usize, usize),         // Set to 1 if a == b, else 0
+  SETNE(usize, usize, usize),         // Set to 1 if a != b, else 0
+  SETLT(usize, usize, usize),         // Set to 1 if a < b, else 0
+  SETLE(usize, usize, usize),         // Set to 1 if a <= b, else 0
+  SETGT(usize, usize, usize),         // Set to 1 if a > b, else 0
+  SETGE(usize, usize, usize),         // Set to 1 if a >= b, else 0
+  TAILCALL(String),                   // Jump to a label reusing the current return address
+  CALL(String),                       // Push the return address and jump to a label
+  RET,                                // Jump back after the last CALL
+  ADDI(usize, usize, i32),            // Addition with an immediate
+  SUBI(usize, usize, i32),            // Substraction of an immediate
+  GCD(usize, usize, usize),           // Greatest common divisor
+  AND(usize, usize, usize),           // Bitwise and
+  OR(usize, usize, usize),            // Bitwise or
+  XOR(usize, usize, usize),           // Bitwise exclusive or
+  NOT(usize, usize),                  // Bitwise complement
+  NEG(usize, usize),                  // Negation
+  SHL(usize, usize, usize),           // Shift left
+  SHR(usize, usize, usize),           // Arithmetic shift right
+  SLT(usize, usize, usize),           // Set to 1 if a < b, else 0 (like MIPS)
+  STEPS(usize),                       // Load the number of executed instructions
+  NOP,                                // No operation, written by the user
+  PRINTC(usize),                      // Print the low byte as an ASCII character
+  SWAP(usize, usize),                 // Exchange the values of both registers
+  MIN(usize, usize, usize),           // Minimum of both values
+  MAX(usize, usize, usize),           // Maximum of both values
+  ABS(usize, usize),                  // Absolute value
+  INC(usize),                         // Add one, wrapping
+  DEC(usize),                         // Subtract one, wrapping
+}
+
+impl Instructions {
+  /// Returns the register written by the instruction, if it writes exactly one.
+  pub fn destination(&self) -> Option<usize> {
+    match self {
+      Instructions::LI(a, _)
+      | Instructions::MOVE(a, _)
+      | Instructions::ABS(a, _)
+      | Instructions::NEG(a, _)
+      | Instructions::NOT(a, _)
+      | Instructions::BSWAP(a, _)
+      | Instructions::RBIT(a, _)
+      | Instructions::ADD(a, _, _)
+      | Instructions::SUB(a, _, _)
+      | Instructions::MUL(a, _, _)
+      | Instructions::DIV(a, _, _)
+      | Instructions::REM(a, _, _)
+      | Instructions::ABSDIFF(a, _, _)
+      | Instructions::MAX(a, _, _)
+      | Instructions::MIN(a, _, _)
+      | Instructions::SLT(a, _, _)
+      | Instructions::SHR(a, _, _)
+      | Instructions::SHL(a, _, _)
+      | Instructions::XOR(a, _, _)
+      | Instructions::OR(a, _, _)
+      | Instructions::AND(a, _, _)
+      | Instructions::GCD(a, _, _)
+      | Instructions::ADDI(a, _, _)
+      | Instructions::SUBI(a, _, _)
+      | Instructions::SETEQ(a, _, _)
+      | Instructions::SETNE(a, _, _)
+      | Instructions::SETLT(a, _, _)
+      | Instructions::SETLE(a, _, _)
+      | Instructions::SETGT(a, _, _)
+      | Instructions::SETGE(a, _, _)
+      | Instructions::POP(a)
+      | Instructions::SP(a)
+      | Instructions::STEPS(a)
+      | Instructions::INC(a)
+      | Instructions::DEC(a)
+      | Instructions::POWMOD(a, _, _, _) => Some(*a),
+      _ => None,
+    }
+  }
+
+  /// Returns the label the instruction may jump to, if any.
+  pub fn target(&self) -> Option<&str> {
+    match self {
+      Instructions::JUMP(a)
+      | Instructions::TAILCALL(a)
+      | Instructions::CALL(a)
+      | Instructions::BOV(a)
+      | Instructions::BEQ(_, _, a)
+      | Instructions::BNE(_, _, a)
+      | Instructions::BLT(_, _, a)
+      | Instructions::BLE(_, _, a)
+      | Instructions::BGT(_, _, a)
+      | Instructions::BGE(_, _, a)
+      | Instructions::BLTU(_, _, a)
+      | Instructions::BLEU(_, _, a)
+      | Instructions::BGTU(_, _, a)
+      | Instructions::BGEU(_, _, a) => Some(a),
+      _ => None,
+    }
+  }
+
+  /// Returns the label the instruction may jump to, if any, to rename it.
+  pub fn target_mut(&mut self) -> Option<&mut String> {
+    match self {
+      Instructions::JUMP(a)
+      | Instructions::TAILCALL(a)
+      | Instructions::CALL(a)
+      | Instructions::BOV(a)
+      | Instructions::BEQ(_, _, a)
+      | Instructions::BNE(_, _, a)
+      | Instructions::BLT(_, _, a)
+      | Instructions::BLE(_, _, a)
+      | Instructions::BGT(_, _, a)
+      | Instructions::BGE(_, _, a)
+      | Instructions::BLTU(_, _, a)
+      | Instructions::BLEU(_, _, a)
+      | Instructions::BGTU(_, _, a)
+      | Instructions::BGEU(_, _, a) => Some(a),
+      _ => None,
+    }
+  }
+
+  /// Returns whether the instruction may change the program counter, other than moving to the next one.
+  pub fn changes_flow(&self) -> bool {
+    self.target().is_some()
+      || matches!(
+        self,
+        Instructions::SKIPEQ(_, _) | Instructions::SKIPNE(_, _) | Instructions::RET
+      )
+  }
+
+  /// Returns the registers read by the instruction.
+  pub fn sources(&self) -> Vec<usize> {
+    match self {
+      Instructions::VADD(_, b, c, d) => span(*b, *d).chain(span(*c, *d)).collect(),
+      Instructions::MOVE(_, b)
+      | Instructions::ABS(_, b)
+      | Instructions::NEG(_, b)
+      | Instructions::NOT(_, b)
+      | Instructions::BSWAP(_, b)
+      | Instructions::RBIT(_, b) => {
+        vec![*b]
+      }
+      Instructions::ADD(_, b, c)
+      | Instructions::MAX(_, b, c)
+      | Instructions::MIN(_, b, c)
+      | Instructions::SLT(_, b, c)
+      | Instructions::SHR(_, b, c)
+      | Instructions::SHL(_, b, c)
+      | Instructions::XOR(_, b, c)
+      | Instructions::OR(_, b, c)
+      | Instructions::AND(_, b, c)
+      | Instructions::GCD(_, b, c)
+      | Instructions::SUB(_, b, c)
+      | Instructions::MUL(_, b, c)
+      | Instructions::DIV(_, b, c)
+      | Instructions::REM(_, b, c)
+      | Instructions::ABSDIFF(_, b, c)
+      | Instructions::SETEQ(_, b, c)
+      | Instructions::SETNE(_, b, c)
+      | Instructions::SETLT(_, b, c)
+      | Instructions::SETLE(_, b, c)
+      | Instructions::SETGT(_, b, c)
+      | Instructions::SETGE(_, b, c) => vec![*b, *c],
+      Instructions::PRINT(a)
+      | Instructions::PRINTNN(a)
+      | Instructions::PRINTC(a)
+      | Instructions::INC(a)
+      | Instructions::DEC(a)
+      | Instructions::PUSH(a) => vec![*a],
+      Instructions::ADDI(_, b, _) | Instructions::SUBI(_, b, _) => vec![*b],
+      Instructions::POWMOD(_, b, c, d) => vec![*b, *c, *d],
+      Instructions::BEQ(a, b, _)
+      | Instructions::BNE(a, b, _)
+      | Instructions::BLT(a, b, _)
+      | Instructions::BLE(a, b, _)
+      | Instructions::BGT(a, b, _)
+      | Instructions::BGE(a, b, _)
+      | Instructions::BLTU(a, b, _)
+      | Instructions::BLEU(a, b, _)
+      | Instructions::BGTU(a, b, _)
+      | Instructions::BGEU(a, b, _)
+      | Instructions::SKIPEQ(a, b)
+      | Instructions::SKIPNE(a, b)
+      | Instructions::SWAP(a, b) => vec![*a, *b],
+      _ => Vec::new(),
+    }
+  }
+
+  /// Returns every register written by the instruction: both of SWAP, the n consecutive ones of VADD
+  /// and READALL, or the single destination of the rest.
+  pub fn destinations(&self) -> Vec<usize> {
+    match self {
+      Instructions::SWAP(a, b) => vec![*a, *b],
+      Instructions::VADD(a, _, _, d) | Instructions::READALL(a, d) => span(*a, *d).collect(),
+      _ => self.destination().into_iter().collect(),
+    }
+  }
+} // impl Instructions
+
+/// Returns the n consecutive registers from start.
+fn span(start: usize, n: usize) -> std::ops::Range<usize> {
+  start..start.saturating_add(n)
 }
 
 impl fmt::Display for Instructions {
@@ -65,43 +403,223 @@ impl fmt::Display for Instructions {
       Instructions::PRINT(a) => write!(f, "PRINT ${a}"),
       Instructions::EXIT => write!(f, "EXIT"),
       Instructions::SKIP => write!(f, "SKIP"),
-      Instructions::JUMP(a) => write!(f, "JUMP @{}", &a),
+      Instructions::JUMP(a) => write!(f, "JUMP {}", &a),
       Instructions::BEQ(a, b, c) => write!(f, "BEQ ${a} ${b} {}", &c),
       Instructions::BNE(a, b, c) => write!(f, "BNE ${a} ${b} {}", &c),
       Instructions::BLT(a, b, c) => write!(f, "BLT ${a} ${b} {}", &c),
       Instructions::BLE(a, b, c) => write!(f, "BLE ${a} ${b} {}", &c),
       Instructions::BGT(a, b, c) => write!(f, "BGT ${a} ${b} {}", &c),
       Instructions::BGE(a, b, c) => write!(f, "BGE ${a} ${b} {}", &c),
+      Instructions::BLTU(a, b, c) => write!(f, "BLTU ${a} ${b} {}", &c),
+      Instructions::BLEU(a, b, c) => write!(f, "BLEU ${a} ${b} {}", &c),
+      Instructions::BGTU(a, b, c) => write!(f, "BGTU ${a} ${b} {}", &c),
+      Instructions::BGEU(a, b, c) => write!(f, "BGEU ${a} ${b} {}", &c),
       Instructions::LABEL => write!(f, "LABEL"),
       Instructions::PUSH(a) => write!(f, "PUSH ${a}"),
       Instructions::POP(a) => write!(f, "POP ${a}"),
+      Instructions::SKIPEQ(a, b) => write!(f, "SKIPEQ ${a} ${b}"),
+      Instructions::SKIPNE(a, b) => write!(f, "SKIPNE ${a} ${b}"),
+      Instructions::ABSDIFF(a, b, c) => write!(f, "ABSDIFF ${a} ${b} ${c}"),
+      Instructions::READALL(a, b) => write!(f, "READALL ${a} {b}"),
+      Instructions::SP(a) => write!(f, "SP ${a}"),
+      Instructions::VADD(a, b, c, d) => write!(f, "VADD ${a} ${b} ${c} {d}"),
+      Instructions::BOV(a) => write!(f, "BOV {}", &a),
+      Instructions::CLO => write!(f, "CLO"),
+      Instructions::PRINTREGS => write!(f, "PRINTREGS"),
+      Instructions::POWMOD(a, b, c, d) => write!(f, "POWMOD ${a} ${b} ${c} ${d}"),
+      Instructions::PRINTNN(a) => write!(f, "PRINTNN ${a}"),
+      Instructions::PRINTLN => write!(f, "PRINTLN"),
+      Instructions::BSWAP(a, b) => write!(f, "BSWAP ${a} ${b}"),
+      Instructions::RBIT(a, b) => write!(f, "RBIT ${a} ${b}"),
+      Instructions::SETEQ(a, b, c) => write!(f, "SETEQ ${a} ${b} ${c}"),
+      Instructions::SETNE(a, b, c) => write!(f, "SETNE ${a} ${b} ${c}"),
+      Instructions::SETLT(a, b, c) => write!(f, "SETLT ${a} ${b} ${c}"),
+      Instructions::SETLE(a, b, c) => write!(f, "SETLE ${a} ${b} ${c}"),
+      Instructions::SETGT(a, b, c) => write!(f, "SETGT ${a} ${b} ${c}"),
+      Instructions::SETGE(a, b, c) => write!(f, "SETGE ${a} ${b} ${c}"),
+      Instructions::TAILCALL(a) => write!(f, "TAILCALL {}", &a),
+      Instructions::CALL(a) => write!(f, "CALL {}", &a),
+      Instructions::RET => write!(f, "RET"),
+      Instructions::ADDI(a, b, c) => write!(f, "ADDI ${a} ${b} {c}"),
+      Instructions::SUBI(a, b, c) => write!(f, "SUBI ${a} ${b} {c}"),
+      Instructions::GCD(a, b, c) => write!(f, "GCD ${a} ${b} ${c}"),
+      Instructions::AND(a, b, c) => write!(f, "AND ${a} ${b} ${c}"),
+      Instructions::OR(a, b, c) => write!(f, "OR ${a} ${b} ${c}"),
+      Instructions::XOR(a, b, c) => write!(f, "XOR ${a} ${b} ${c}"),
+      Instructions::NOT(a, b) => write!(f, "NOT ${a} ${b}"),
+      Instructions::NEG(a, b) => write!(f, "NEG ${a} ${b}"),
+      Instructions::SHL(a, b, c) => write!(f, "SHL ${a} ${b} ${c}"),
+      Instructions::SHR(a, b, c) => write!(f, "SHR ${a} ${b} ${c}"),
+      Instructions::SLT(a, b, c) => write!(f, "SLT ${a} ${b} ${c}"),
+      Instructions::STEPS(a) => write!(f, "STEPS ${a}"),
+      Instructions::NOP => write!(f, "NOP"),
+      Instructions::PRINTC(a) => write!(f, "PRINTC ${a}"),
+      Instructions::SWAP(a, b) => write!(f, "SWAP ${a} ${b}"),
+      Instructions::MIN(a, b, c) => write!(f, "MIN ${a} ${b} ${c}"),
+      Instructions::MAX(a, b, c) => write!(f, "MAX ${a} ${b} ${c}"),
+      Instructions::ABS(a, b) => write!(f, "ABS ${a} ${b}"),
+      Instructions::INC(a) => write!(f, "INC ${a}"),
+      Instructions::DEC(a) => write!(f, "DEC ${a}"),
     }
   }
 }
 
-/// Enum representing all the possible errors during runtime.
+/// Phase of the simulator where an error comes from.
 #[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ErrorKind {
+  /// Reading and parsing the program (`load`).
+  Parse,
+  /// Executing the program (`run`).
+  Runtime,
+}
+
+/// Enum representing all the possible errors during runtime.
+/// It is `Clone` but no longer `Copy`: some variants carry the text they are about, like the unexpected token;
+/// copy it with `clone()`, or match it by reference.
+#[derive(Clone, Debug, PartialEq)]
 pub enum Error {
   OutOfRange,
+  RegisterOutOfRange {
+    register: usize,
+    pc: usize,
+  },
   DivisionByZero,
   MainNotFound,
   UnknownLabel,
   InvalidInstruction,
   InvalidParameter,
-  EmptyStack,
+  UnexpectedToken(String),
+  StackUnderflow,
+  MissingLabelColon,
+  OutOfFuel,
+  ReadFailed,
+  InputFailed {
+    pc: usize,
+  },
+  InvalidEncoding,
+  OutputMismatch {
+    line: usize,
+    expected: Option<String>,
+    actual: Option<String>,
+  },
+  EndOfInput,
+  InvalidInput,
+  StackImbalance,
+  OutputClosed,
+  DuplicateLabel,
+  LineTooLong {
+    line: usize,
+  },
+  UnusedLabel,
+  LabelOutOfRange {
+    index: usize,
+  },
+  UnboundedWrap,
 }
 
+impl Error {
+  /// Returns the phase where the error is produced.
+  pub fn kind(&self) -> ErrorKind {
+    match self {
+      Error::InvalidInstruction
+      | Error::InvalidParameter
+      | Error::UnexpectedToken(_)
+      | Error::MissingLabelColon
+      | Error::ReadFailed
+      | Error::InvalidEncoding
+      | Error::DuplicateLabel
+      | Error::LineTooLong { .. }
+      | Error::UnusedLabel
+      | Error::LabelOutOfRange { .. } => ErrorKind::Parse,
+      _ => ErrorKind::Runtime,
+    }
+  }
+
+  /// Returns a stable identifier of the kind of error, for tooling.
+  pub fn code(&self) -> &'static str {
+    match self {
+      Error::OutOfRange => "out-of-range",
+      Error::RegisterOutOfRange { .. } => "register-out-of-range",
+      Error::DivisionByZero => "division-by-zero",
+      Error::MainNotFound => "main-not-found",
+      Error::UnknownLabel => "unknown-label",
+      Error::InvalidInstruction => "invalid-instruction",
+      Error::InvalidParameter => "invalid-parameter",
+      Error::UnexpectedToken(_) => "unexpected-token",
+      Error::StackUnderflow => "stack-underflow",
+      Error::MissingLabelColon => "missing-label-colon",
+      Error::OutOfFuel => "out-of-fuel",
+      Error::ReadFailed => "read-failed",
+      Error::InputFailed { .. } => "input-failed",
+      Error::InvalidEncoding => "invalid-encoding",
+      Error::OutputMismatch { .. } => "output-mismatch",
+      Error::EndOfInput => "end-of-input",
+      Error::InvalidInput => "invalid-input",
+      Error::StackImbalance => "stack-imbalance",
+      Error::OutputClosed => "output-closed",
+      Error::DuplicateLabel => "duplicate-label",
+      Error::LineTooLong { .. } => "line-too-long",
+      Error::UnusedLabel => "unused-label",
+      Error::LabelOutOfRange { .. } => "label-out-of-range",
+      Error::UnboundedWrap => "unbounded-wrap",
+    }
+  }
+} // impl Error
+
 /// trait for verbose errors.
 impl fmt::Display for Error {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     match self {
       Error::DivisionByZero => f.write_str("division by zero"),
       Error::OutOfRange => f.write_str("the reg is out of the ranges"),
+      Error::RegisterOutOfRange { register, pc } => {
+        write!(f, "register ${register} out of range at instruction {pc}")
+      }
       Error::MainNotFound => f.write_str("main label not found"),
       Error::UnknownLabel => f.write_str("trying to jump to a unknown label. Label not found"),
       Error::InvalidInstruction => f.write_str("the instruction is not valid, or doesn't exist"),
-      Error::InvalidParameter => f.write_str("the parameters are not valid"),
-      Error::EmptyStack => f.write_str("trying to pop when the stack is empty"),
+      Error::UnexpectedToken(token) => write!(f, "unexpected token `{token}` after the operands"),
+      Error::InvalidParameter => f.write_str("the parameters are not valid, or missing"),
+      Error::StackUnderflow => f.write_str("trying to pop when the stack is empty"),
+      Error::MissingLabelColon => f.write_str("the label definition must end with a colon"),
+      Error::OutOfFuel => f.write_str("the execution ran out of fuel"),
+      Error::ReadFailed => f.write_str("error reading the program source"),
+      Error::InputFailed { pc } => write!(f, "error reading the input at instruction {pc}"),
+      Error::InvalidEncoding => f.write_str("the program source is not valid UTF-8"),
+      Error::OutputMismatch {
+        line,
+        expected,
+        actual,
+      } => {
+        let text = |x: &Option<String>| match x {
+          Some(text) => format!("`{text}`"),
+          None => String::from("the end of the output"),
+        };
+        write!(
+          f,
+          "the output differs from the expected one at line {line}: expected {}, got {}",
+          text(expected),
+          text(actual)
+        )
+      }
+      Error::EndOfInput => f.write_str("trying to read when the input has ended"),
+      Error::InvalidInput => f.write_str("the input is not a valid integer"),
+      Error::StackImbalance => {
+        f.write_str("the stack or the call stack is not empty at the end of the program")
+      }
+      Error::OutputClosed => f.write_str("the receiver of the output channel was dropped"),
+      Error::DuplicateLabel => f.write_str("the label is defined in more than one program"),
+      Error::LineTooLong { line } => write!(f, "line {line} is longer than the maximum"),
+      Error::UnusedLabel => f.write_str("a label is never referenced by any jump"),
+      Error::UnboundedWrap => {
+        f.write_str("the program counter wraps around, so the run needs a step limit")
+      }
+      Error::LabelOutOfRange { index } => {
+        write!(
+          f,
+          "a label points to instruction {index}, past the end of the program"
+        )
+      }
     }
   }
 } // impl fmt::Display for Error
@@ -112,31 +630,1498 @@ impl Simulator {
     Simulator::default()
   }
 
+  /// Sets the policy applied when an instruction fails.
+  pub fn with_error_policy(mut self, policy: ErrorPolicy) -> Self {
+    self.error_policy = policy;
+    self
+  }
+
+  /// Returns the errors recovered under `ErrorPolicy::Continue`, with the instruction index where they happened.
+  pub fn runtime_warnings(&self) -> &[(usize, Error)] {
+    &self.runtime_warnings
+  }
+
+  /// Requires label definitions to end with a colon (`@MAIN:`). By default both forms are accepted.
+  pub fn with_label_colons(mut self, required: bool) -> Self {
+    self.label_colons = required;
+    self
+  }
+
+  /// Reads the program input from the text instead of stdin.
+  pub fn with_input(mut self, text: &str) -> Self {
+    self.input = Input::from_text(text);
+    self
+  }
+
+  /// Sets how the printed register values are written. Decimal by default.
+  pub fn with_value_format(mut self, format: ValueFormat) -> Self {
+    self.value_format = format;
+    self
+  }
+
+  /// Makes a program that ends with values left in the stack, or inside a CALL, fail with `Error::StackImbalance`.
+  pub fn with_stack_balance_check(mut self, enabled: bool) -> Self {
+    self.stack_balance_check = enabled;
+    self
+  }
+
+  /// Captures all the output in an internal buffer instead of writing it to stdout.
+  pub fn with_captured_output(mut self) -> Self {
+    self.output = Output::Captured(String::new());
+    self
+  }
+
+  /// Sends the output to the channel as `PrintEvent`s instead of writing it.
+  /// Printing after the receiver is dropped fails with `Error::OutputClosed`.
+  pub fn with_output_channel(mut self, sender: Sender<PrintEvent>) -> Self {
+    self.output = Output::Channel(sender);
+    self
+  }
+
+  /// Discards all the output, without formatting the printed values.
+  pub fn with_null_output(mut self) -> Self {
+    self.output = Output::Null;
+    self
+  }
+
+  /// Returns and clears the captured output.
+  pub fn take_output(&mut self) -> String {
+    self.output.take()
+  }
+
+  /// Fills the registers with the value, here and on `reset`, to make reads of unset registers visible.
+  pub fn with_register_init(mut self, value: i32) -> Self {
+    self.register_init = value;
+    self.int_registers = [value; INT_REGISTER_COUNT];
+    self
+  }
+
+  /// Wraps the program counter back to 0 when it runs off the end of the program, instead of halting.
+  /// The program then only ends on EXIT or an error, so it must run with a step limit:
+  /// `run_with_fuel`, `step_n`, `run_slice` or `run_yielding`. The other runs fail with `Error::UnboundedWrap`.
+  pub fn with_pc_wrap(mut self, enabled: bool) -> Self {
+    self.pc_wrap = enabled;
+    self
+  }
+
+  /// Makes loading fail with `Error::UnusedLabel` if a label other than `@MAIN` is never jumped to.
+  /// By default unused labels are only reported by `unused_labels` and `lint`.
+  pub fn with_require_label_use(mut self, required: bool) -> Self {
+    self.require_label_use = required;
+    self
+  }
+
+  /// Sets the longest source line accepted, in bytes. `DEFAULT_MAX_LINE_LENGTH` by default.
+  pub fn with_max_line_length(mut self, max: usize) -> Self {
+    self.max_line_length = Some(max);
+    self
+  }
+
+  /// Fails with `Error::LineTooLong` if any raw line is over the maximum length.
+  fn check_line_length(&self, raw_lines: &[String]) -> Result<(), Error> {
+    check_line_length(
+      raw_lines,
+      self.max_line_length.unwrap_or(DEFAULT_MAX_LINE_LENGTH),
+    )
+  }
+
+  /// Writes the load progress messages (`Preprocess... Done`) to stderr. Disabled by default.
+  pub fn with_verbose(mut self, enabled: bool) -> Self {
+    self.verbose = enabled;
+    self
+  }
+
+  /// Writes the text to stderr only in verbose mode, so it never mixes with the program output.
+  fn log(&self, text: &str) {
+    if self.verbose {
+      eprint!("{text}");
+    }
+  }
+
+  /// Returns the number of instructions executed since the last start of a run.
+  pub fn steps(&self) -> u64 {
+    self.steps
+  }
+
+  /// Clears the execution state (registers, program counter, stacks and overflow flag), keeping the program.
+  pub fn reset(&mut self) {
+    self.int_registers = [self.register_init; INT_REGISTER_COUNT];
+    self.program_counter = 0;
+    self.stack = Stack::new();
+    self.call_stack = Stack::new();
+    self.overflow = false;
+    self.runtime_warnings.clear();
+    self.in_slice = false;
+  }
+
+  /// Enables or disables the recording of an execution trace.
+  pub fn with_trace(mut self, enabled: bool) -> Self {
+    self.trace = if enabled { Some(Vec::new()) } else { None };
+    self
+  }
+
+  /// Returns the recorded trace as CSV, with the columns `step,pc,mnemonic,changed_reg,new_value`.
+  pub fn trace_csv(&self) -> String {
+    let mut output = String::from("step,pc,mnemonic,changed_reg,new_value\n");
+    for entry in self.trace.iter().flatten() {
+      let (reg, value) = match entry.changed_reg {
+        Some((reg, value)) => (reg.to_string(), value.to_string()),
+        None => (String::new(), String::new()),
+      };
+      output.push_str(&format!(
+        "{},{},{},{reg},{value}\n",
+        entry.step, entry.pc, entry.mnemonic
+      ));
+    }
+    output
+  }
+
+  /// Returns the pairs (writer, reader) of instruction indices where the reader uses a register
+  /// last written by the writer, following the program in textual order.
+  pub fn dependencies(&self) -> Vec<(usize, usize)> {
+    let mut last_writer: HashMap<usize, usize> = HashMap::new();
+    let mut pairs = Vec::new();
+    for (index, instruction) in self.instructions.iter().enumerate() {
+      for source in instruction.sources() {
+        if let Some(writer) = last_writer.get(&source)
+          && !pairs.contains(&(*writer, index))
+        {
+          pairs.push((*writer, index));
+        }
+      }
+      for destination in instruction.destinations() {
+        last_writer.insert(destination, index);
+      }
+    }
+    pairs
+  }
+
+  /// Returns the inline comment written with the instruction, if any.
+  pub fn comment(&self, idx: usize) -> Option<&str> {
+    self.comments.get(&idx).map(|x| x.as_str())
+  }
+
+  /// Returns the output declared by the program with `//! OUTPUT: ...` comments, one line each.
+  pub fn expected_output(&self) -> String {
+    self
+      .expected_output
+      .iter()
+      .map(|x| format!("{x}\n"))
+      .collect()
+  }
+
+  /// Returns the deepest the call stack has been since the last start of a run.
+  pub fn max_call_depth(&self) -> usize {
+    self.max_call_depth
+  }
+
+  /// Returns the index of the next instruction to execute.
+  pub fn program_counter(&self) -> usize {
+    self.program_counter
+  }
+
+  /// Returns the next instruction to execute, or None if the program has ended.
+  pub fn current_instruction(&self) -> Option<&Instructions> {
+    self.instructions.get(self.program_counter)
+  }
+
+  /// Returns the deepest the stack has been since the last start of a run.
+  pub fn max_stack_depth(&self) -> usize {
+    self.max_stack_depth
+  }
+
+  /// Saves the registers, the program counter, the stacks and the overflow flag.
+  pub fn checkpoint(&self) -> Checkpoint {
+    Checkpoint {
+      int_registers: self.int_registers,
+      program_counter: self.program_counter,
+      stack: self.stack.clone(),
+      call_stack: self.call_stack.clone(),
+      overflow: self.overflow,
+      exited: self.exited,
+    }
+  }
+
+  /// Restores the state saved in the checkpoint.
+  pub fn restore(&mut self, cp: &Checkpoint) {
+    self.int_registers = cp.int_registers;
+    self.program_counter = cp.program_counter;
+    self.stack = cp.stack.clone();
+    self.call_stack = cp.call_stack.clone();
+    self.overflow = cp.overflow;
+    self.exited = cp.exited;
+  }
+
+  /// Defines a new label (`END` or `@END`) before the instruction at the index of the loaded program,
+  /// so a jump to it runs that instruction next.
+  /// Like in the text format, a LABEL instruction is inserted, moving the later instructions, labels and comments one place.
+  /// Fails with `Error::DuplicateLabel` if the label already exists, or `Error::OutOfRange` if there is no such instruction.
+  pub fn insert_label(&mut self, name: &str, at: usize) -> Result<(), Error> {
+    if at >= self.instructions.len() {
+      return Err(Error::OutOfRange);
+    }
+    let name = parser::normalize_label(name);
+    if self.labels.contains_key(&name) {
+      return Err(Error::DuplicateLabel);
+    }
+    self.instructions.insert(at, Instructions::LABEL);
+    for index in self.labels.values_mut().filter(|x| **x >= at) {
+      *index += 1;
+    }
+    self.comments = self
+      .comments
+      .drain()
+      .map(|(index, text)| (if index >= at { index + 1 } else { index }, text))
+      .collect();
+    if self.program_counter > at {
+      self.program_counter += 1;
+    }
+    self.labels.insert(name, at);
+    Ok(())
+  }
+
+  /// Returns the labels that no instruction jumps to, excluding `@MAIN`.
+  pub fn unused_labels(&self) -> Vec<String> {
+    let targets: Vec<&str> = self
+      .instructions
+      .iter()
+      .filter_map(|x| x.target())
+      .collect();
+    self
+      .sorted_labels()
+      .into_iter()
+      .filter(|(_, name)| *name != "@MAIN" && !targets.contains(name))
+      .map(|(_, name)| name.to_owned())
+      .collect()
+  }
+
+  /// Returns the (instruction, target) edges of every jump, with the label resolved to its index.
+  /// Conditional jumps and calls also have the edge to the next instruction, if there is one.
+  /// SKIPEQ and SKIPNE have the edges to the next two instructions, the ones that exist.
+  /// RET has no edges, as its target is only known at runtime.
+  /// Jumps to unknown labels have no taken edge.
+  pub fn jump_targets(&self) -> Vec<(usize, usize)> {
+    let mut edges = Vec::new();
+    let len = self.instructions.len();
+    for (index, instruction) in self.instructions.iter().enumerate() {
+      if matches!(
+        instruction,
+        Instructions::SKIPEQ(_, _) | Instructions::SKIPNE(_, _)
+      ) {
+        edges.extend(
+          (index + 1..index + 3)
+            .filter(|&x| x < len)
+            .map(|x| (index, x)),
+        );
+        continue;
+      }
+      let Some(label) = instruction.target() else {
+        continue;
+      };
+      if let Some(&target) = self.labels.get(label) {
+        edges.push((index, target));
+      }
+      let conditional = !matches!(
+        instruction,
+        Instructions::JUMP(_) | Instructions::TAILCALL(_)
+      );
+      if conditional && index + 1 < self.instructions.len() {
+        edges.push((index, index + 1));
+      }
+    }
+    edges
+  }
+
+  /// Returns the labels sorted by (index, name), so any output derived from them is deterministic.
+  fn sorted_labels(&self) -> Vec<(usize, &str)> {
+    let mut labels: Vec<(usize, &str)> =
+      self.labels.iter().map(|(k, v)| (*v, k.as_str())).collect();
+    labels.sort();
+    labels
+  }
+
+  /// Returns the loaded program as text, one label or instruction per line.
+  pub fn disassemble(&self) -> String {
+    let labels = self.sorted_labels();
+    let mut output = String::new();
+    for (index, instruction) in self.instructions.iter().enumerate() {
+      let names: Vec<&str> = labels
+        .iter()
+        .filter(|x| x.0 == index)
+        .map(|x| x.1)
+        .collect();
+      for name in &names {
+        output.push_str(&format!("{name}\n"));
+      }
+      match instruction {
+        Instructions::LABEL if !names.is_empty() => (),
+        Instructions::LABEL => output.push_str("SKIP\n"),
+        _ => output.push_str(&format!("  {instruction}\n")),
+      }
+    }
+    output
+  }
+
+  /// Checks the just loaded program and records its properties.
+  fn after_load(&mut self) -> Result<(), Error> {
+    self.in_slice = false;
+    self.update_linear();
+    self.validate_labels()?;
+    if self.require_label_use && !self.unused_labels().is_empty() {
+      Err(Error::UnusedLabel)
+    } else {
+      Ok(())
+    }
+  }
+
+  /// Checks that every label points to an instruction of the loaded program.
+  /// Every load runs it, so the error is a Parse one; hosts can call it again after editing the program.
+  pub fn validate_labels(&self) -> Result<(), Error> {
+    match self
+      .labels
+      .values()
+      .find(|&&x| x >= self.instructions.len())
+    {
+      Some(&index) => Err(Error::LabelOutOfRange { index }),
+      None => Ok(()),
+    }
+  }
+
+  /// Records whether the loaded program runs straight from start to end, without jumps or skips.
+  fn update_linear(&mut self) {
+    self.is_linear = !self.instructions.iter().any(Instructions::changes_flow);
+  }
+
+  /// Runs a program without jumps or skips, avoiding the per-step checks of `step`.
+  fn run_linear(&mut self) -> Result<(), Error> {
+    let len = self.instructions.len();
+    while self.program_counter < len {
+      self.steps += 1;
+      operation::operate(self)?;
+      if self.exited {
+        break;
+      }
+      self.program_counter += 1;
+    }
+    self.program_counter = len;
+    Ok(())
+  }
+
+  /// Executes the instruction at the program counter.
+  /// With debug, the instruction is written to stderr, so it never mixes with the program output.
   fn step(&mut self, debug: bool) -> Result<(), Error> {
-    if debug {
-      println!("{}", self.instructions[self.program_counter]);
+    if let (true, Some(instruction)) = (debug, self.instructions.get(self.program_counter)) {
+      eprintln!("{instruction}");
+    }
+    let pc = self.program_counter;
+    let previous = self.trace.is_some().then_some(self.int_registers);
+    self.steps += 1;
+    if let Err(err) = operation::operate(self) {
+      match self.error_policy {
+        ErrorPolicy::Abort => return Err(err),
+        ErrorPolicy::Continue => self.runtime_warnings.push((self.program_counter, err)),
+      }
+    }
+    // Under `ErrorPolicy::Continue` the program counter may point past the program: nothing ran to trace.
+    if let (Some(trace), Some(previous), Some(instruction)) =
+      (self.trace.as_mut(), previous, self.instructions.get(pc))
+    {
+      let changed_reg = (0..INT_REGISTER_COUNT)
+        .find(|&x| self.int_registers[x] != previous[x])
+        .map(|x| (x, self.int_registers[x]));
+      let instruction = instruction.to_string();
+      trace.push(TraceEntry {
+        step: trace.len(),
+        pc,
+        mnemonic: instruction
+          .split_whitespace()
+          .next()
+          .unwrap_or_default()
+          .to_string(),
+        changed_reg,
+      });
+    }
+    if self.exited {
+      self.program_counter = self.instructions.len();
+    } else {
+      self.program_counter += 1;
+      if self.pc_wrap && self.program_counter >= self.instructions.len() {
+        self.program_counter = 0;
+      }
     }
-    operation::operate(self)?;
-    self.program_counter += 1;
     Ok(())
   }
 
+  /// Loads the program lines. In verbose mode, the progress is written to stderr, apart from the program output.
   pub fn load(&mut self, raw_lines: &[String]) -> Result<(), Error> {
-    print!("Preprocess...");
+    self.log("Preprocess...");
+    self.check_line_length(raw_lines)?;
+    self
+      .expected_output
+      .extend(collect_expected_output(raw_lines));
     let preprocess = preprocess_lines(raw_lines);
-    println!(" Done");
+    self.log(" Done\n");
 
-    print!("Parsing...");
+    self.log("Parsing...");
     process_lines(&preprocess, self)?;
-    println!(" Done");
+    self.log(" Done\n");
+    self.after_load()
+  }
+
+  /// Loads the lines like `load`, but comments and empty lines become SKIP instructions,
+  /// so each source line keeps its index. The text of the comments is available with `comment`.
+  pub fn load_preserving_comments(&mut self, raw_lines: &[String]) -> Result<(), Error> {
+    self.check_line_length(raw_lines)?;
+    self
+      .expected_output
+      .extend(collect_expected_output(raw_lines));
+    process_lines(&preserve_comment_lines(raw_lines), self)?;
+    self.after_load()
+  }
+
+  /// Loads the lines like `load`, calling `on_progress(parsed, total)` after each chunk of lines.
+  /// `total` counts the lines left after removing comments and empty lines.
+  pub fn load_with_progress(
+    &mut self, raw_lines: &[String], mut on_progress: impl FnMut(usize, usize),
+  ) -> Result<(), Error> {
+    const CHUNK_SIZE: usize = 256;
+    self.check_line_length(raw_lines)?;
+    self
+      .expected_output
+      .extend(collect_expected_output(raw_lines));
+    let preprocess = preprocess_lines(raw_lines);
+    let total = preprocess.len();
+    on_progress(0, total);
+    let mut parsed = 0;
+    for chunk in preprocess.chunks(CHUNK_SIZE) {
+      process_lines(chunk, self)?;
+      parsed += chunk.len();
+      on_progress(parsed, total);
+    }
+    self.after_load()
+  }
+
+  /// Reads the whole source, decodes it as UTF-8 and loads its lines.
+  pub fn load_reader<R: Read>(&mut self, mut reader: R) -> Result<(), Error> {
+    let mut bytes = Vec::new();
+    reader
+      .read_to_end(&mut bytes)
+      .map_err(|_| Error::ReadFailed)?;
+    let source = String::from_utf8(bytes).map_err(|_| Error::InvalidEncoding)?;
+    let lines: Vec<String> = source.lines().map(|x| x.to_string()).collect();
+    self.load(&lines)
+  }
+
+  /// Parses and executes one instruction against the current state, without adding it to the program.
+  /// Instructions that depend on the program (jumps, calls, skips and EXIT) are rejected.
+  pub fn eval(&mut self, line: &str) -> Result<(), Error> {
+    let instruction = parse_instruction(line)?;
+    match instruction {
+      _ if instruction.changes_flow() => Err(Error::InvalidInstruction),
+      Instructions::EXIT => Err(Error::InvalidInstruction),
+      _ => operation::execute(self, instruction),
+    }
+  }
+
+  /// Sets the program counter at the main label, ready to run or step.
+  pub fn start(&mut self) -> Result<(), Error> {
+    self.program_counter = *self.labels.get("@MAIN").ok_or(Error::MainNotFound)?;
+    self.runtime_warnings.clear();
+    self.steps = 0;
+    self.exited = false;
+    self.call_stack = Stack::new();
+    self.max_stack_depth = self.stack.len();
+    self.max_call_depth = 0;
+    self.pending_separator = false;
+    self.in_slice = false;
     Ok(())
   }
 
+  /// Fails with `Error::UnboundedWrap` in pc wrap mode, where a run without step limit may never end.
+  fn check_bounded(&self) -> Result<(), Error> {
+    if self.pc_wrap {
+      Err(Error::UnboundedWrap)
+    } else {
+      Ok(())
+    }
+  }
+
+  /// Checks the state once the program has ended.
+  fn finish(&self) -> Result<(), Error> {
+    if self.stack_balance_check && !(self.stack.is_empty() && self.call_stack.is_empty()) {
+      Err(Error::StackImbalance)
+    } else {
+      Ok(())
+    }
+  }
+
   pub fn run(&mut self, debug: bool) -> Result<(), Error> {
-    self.program_counter = self.labels.get("@MAIN").ok_or(Error::MainNotFound)?.clone();
+    self.check_bounded()?;
+    self.start()?;
+    if self.is_linear
+      && !debug
+      && !self.pc_wrap
+      && self.trace.is_none()
+      && self.error_policy == ErrorPolicy::Abort
+    {
+      self.run_linear()?;
+    }
     while self.program_counter < self.instructions.len() {
       self.step(debug)?;
     }
-    Ok(())
+    self.finish()
+  }
+
+  /// Loads and runs the lines in a new simulator, capturing the output and counting the steps.
+  pub fn execute(lines: &[String]) -> RunResult {
+    let mut sim = Simulator::new().with_captured_output();
+    let error = sim.load(lines).and_then(|_| sim.run(false)).err();
+    RunResult {
+      output: sim.take_output(),
+      final_registers: sim.int_registers.to_vec(),
+      steps: sim.steps,
+      error,
+    }
+  }
+
+  /// Runs the program capturing its output, and compares it with the expected one.
+  /// Returns `Error::OutputMismatch` with the first differing line (starting at 1) and both texts of it,
+  /// `None` for the side that has already ended.
+  pub fn run_expecting(&mut self, expected: &str) -> Result<(), Error> {
+    let previous = std::mem::replace(&mut self.output, Output::Captured(String::new()));
+    let result = self.run(false);
+    let actual = std::mem::replace(&mut self.output, previous).take();
+    result?;
+    let mut actual_lines = actual.lines();
+    let mut expected_lines = expected.lines();
+    let mut line = 1;
+    loop {
+      match (actual_lines.next(), expected_lines.next()) {
+        (None, None) => return Ok(()),
+        (a, e) if a == e => line += 1,
+        (a, e) => {
+          return Err(Error::OutputMismatch {
+            line,
+            expected: e.map(String::from),
+            actual: a.map(String::from),
+          });
+        }
+      }
+    }
+  }
+
+  /// Runs the program calling `on_yield` every `every` steps (never if it is 0).
+  /// Returning `false` from the callback stops the execution, returning `Ok`.
+  pub fn run_yielding(
+    &mut self, every: u64, mut on_yield: impl FnMut(&Simulator) -> bool,
+  ) -> Result<(), Error> {
+    if every == 0 {
+      self.check_bounded()?;
+    }
+    self.start()?;
+    let mut steps: u64 = 0;
+    while self.program_counter < self.instructions.len() {
+      self.step(false)?;
+      steps += 1;
+      if every != 0 && steps.is_multiple_of(every) && !on_yield(self) {
+        return Ok(());
+      }
+    }
+    self.finish()
+  }
+
+  /// Runs the program until the register holds the value (checked after each step) or the program ends.
+  pub fn run_until_register(&mut self, reg: usize, value: i32) -> Result<RunState, Error> {
+    if reg >= INT_REGISTER_COUNT {
+      return Err(Error::OutOfRange);
+    }
+    self.check_bounded()?;
+    self.start()?;
+    while self.program_counter < self.instructions.len() {
+      self.step(false)?;
+      if self.int_registers[reg] == value {
+        return Ok(RunState::Paused);
+      }
+    }
+    self.finish()?;
+    Ok(RunState::Halted)
+  }
+
+  /// Executes the instruction at the program counter, returning what it changed.
+  pub fn step_diff(&mut self) -> Result<StepDiff, Error> {
+    let before = self.checkpoint();
+    self.step(false)?;
+    let registers = (0..INT_REGISTER_COUNT)
+      .filter(|&x| self.int_registers[x] != before.int_registers[x])
+      .map(|x| (x, before.int_registers[x], self.int_registers[x]))
+      .collect();
+    let stack_depth =
+      (before.stack.len() != self.stack.len()).then(|| (before.stack.len(), self.stack.len()));
+    Ok(StepDiff {
+      pc: (before.program_counter, self.program_counter),
+      registers,
+      stack_depth,
+    })
+  }
+
+  /// Runs up to budget instructions, returning `RunState::Paused` if the program has not ended yet.
+  /// The first call starts the program; the following ones resume where the last one stopped,
+  /// until it returns `RunState::Halted` or an error.
+  pub fn run_slice(&mut self, budget: u64) -> Result<RunState, Error> {
+    if !self.in_slice {
+      self.start()?;
+      self.in_slice = true;
+    }
+    let outcome = self.step_n(budget).inspect_err(|_| self.in_slice = false)?;
+    if outcome.halted {
+      self.in_slice = false;
+      self.finish()?;
+      Ok(RunState::Halted)
+    } else {
+      Ok(RunState::Paused)
+    }
+  }
+
+  /// Executes up to n instructions from the current program counter, stopping early if the program ends.
+  pub fn step_n(&mut self, n: u64) -> Result<StepOutcome, Error> {
+    let mut executed = 0;
+    while executed < n && self.program_counter < self.instructions.len() {
+      self.step(false)?;
+      executed += 1;
+    }
+    Ok(StepOutcome {
+      executed,
+      halted: self.program_counter >= self.instructions.len(),
+    })
+  }
+
+  /// Runs the program spending one unit of fuel per instruction.
+  /// Returns the remaining fuel, or `Error::OutOfFuel` if it runs out before the program ends.
+  pub fn run_with_fuel(&mut self, mut fuel: u64) -> Result<u64, Error> {
+    self.start()?;
+    while self.program_counter < self.instructions.len() {
+      if fuel == 0 {
+        return Err(Error::OutOfFuel);
+      }
+      self.step(false)?;
+      fuel -= 1;
+    }
+    self.finish()?;
+    Ok(fuel)
   }
 } // impl Simulator
+
+#[cfg(test)]
+mod simulator_test {
+  use crate::simulator::output::PrintEvent;
+  use crate::simulator::{
+    DEFAULT_MAX_LINE_LENGTH, Error, ErrorKind, ErrorPolicy, INT_REGISTER_COUNT, Instructions,
+    RunState, Simulator, StepDiff, StepOutcome,
+  };
+  use std::{io::Cursor, sync::mpsc::channel};
+
+  fn lines(src: &str) -> Vec<String> {
+    src.lines().map(|x| x.to_string()).collect()
+  }
+
+  #[test]
+  fn register_count_test() {
+    let sim = Simulator::new();
+    assert_eq!(INT_REGISTER_COUNT, sim.int_registers.len());
+  }
+
+  #[test]
+  fn continue_policy_test() {
+    let program = lines("@MAIN\nLI $1 4\nDIV $2 $1 $0\nREM $3 $1 $0\nLI $4 7");
+    let mut sim = Simulator::new().with_error_policy(ErrorPolicy::Continue);
+    sim.load(&program).unwrap();
+    sim.run(false).unwrap();
+    assert_eq!(
+      sim.runtime_warnings(),
+      &[(2, Error::DivisionByZero), (3, Error::DivisionByZero)]
+    );
+    assert_eq!(sim.int_registers[4], 7);
+  }
+
+  #[test]
+  fn abort_policy_test() {
+    let program = lines("@MAIN\nLI $1 4\nDIV $2 $1 $0");
+    let mut sim = Simulator::new();
+    sim.load(&program).unwrap();
+    assert_eq!(sim.run(false), Err(Error::DivisionByZero));
+    assert!(sim.runtime_warnings().is_empty());
+  }
+
+  #[test]
+  fn disassemble_test() {
+    let program = lines("@LOOP\nJUMP @END\n@MAIN\nLI $1 2\n@END\n@B\n@A\nPRINT $1");
+    let mut first = Simulator::new();
+    first.load(&program).unwrap();
+    let mut second = Simulator::new();
+    second.load(&program).unwrap();
+    assert_eq!(first.disassemble(), second.disassemble());
+    assert!(first.disassemble().starts_with("@LOOP\n"));
+  }
+
+  #[test]
+  fn error_kind_test() {
+    let mut sim = Simulator::new();
+    let err = sim.load(&lines("@MAIN\nPRINT $1 $2")).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Parse);
+    let err = sim.load(&lines("@MAIN\nFOO $1")).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Parse);
+
+    let mut sim = Simulator::new();
+    sim.load(&lines("@MAIN\nPOP $1")).unwrap();
+    assert_eq!(sim.run(false).unwrap_err().kind(), ErrorKind::Runtime);
+    let mut sim = Simulator::new();
+    sim.load(&lines("LI $1 1")).unwrap();
+    assert_eq!(sim.run(false).unwrap_err().kind(), ErrorKind::Runtime);
+
+    let err = Error::InputFailed { pc: 3 };
+    assert_eq!(err.kind(), ErrorKind::Runtime);
+    assert_eq!(err.code(), "input-failed");
+    assert_eq!(err.to_string(), "error reading the input at instruction 3");
+  }
+
+  #[test]
+  fn load_with_progress_test() {
+    let mut program = lines("@MAIN\n// comment");
+    program.extend((0..600).map(|x| format!("LI $1 {x}")));
+    let mut sim = Simulator::new();
+    let mut calls = Vec::new();
+    sim
+      .load_with_progress(&program, |parsed, total| calls.push((parsed, total)))
+      .unwrap();
+    assert_eq!(calls, vec![(0, 601), (256, 601), (512, 601), (601, 601)]);
+    assert_eq!(sim.instructions.len(), 601);
+    assert_eq!(sim.instructions[600], Instructions::LI(1, 599));
+  }
+
+  #[test]
+  fn load_reader_test() {
+    let source = Cursor::new(b"@MAIN\nLI $1 5\n".to_vec());
+    let mut sim = Simulator::new();
+    sim.load_reader(source).unwrap();
+    assert_eq!(sim.instructions[1], Instructions::LI(1, 5));
+
+    let source = Cursor::new(b"\xef\xbb\xbf@MAIN\nLI $1 6\n".to_vec());
+    let mut sim = Simulator::new();
+    sim.load_reader(source).unwrap();
+    assert_eq!(sim.labels["@MAIN"], 0);
+    assert_eq!(sim.run(false), Ok(()));
+    assert_eq!(sim.int_registers[1], 6);
+
+    let source = Cursor::new(vec![b'@', 0xff, b'\n']);
+    assert_eq!(
+      Simulator::new().load_reader(source),
+      Err(Error::InvalidEncoding)
+    );
+  }
+
+  #[test]
+  fn eval_test() {
+    let mut sim = Simulator::new();
+    sim.eval("LI $1 5").unwrap();
+    sim.eval("ADD $2 $1 $1").unwrap();
+    assert_eq!(sim.int_registers[1..3], [5, 10]);
+    assert!(sim.instructions.is_empty());
+    assert_eq!(sim.eval("JUMP @MAIN"), Err(Error::InvalidInstruction));
+    assert_eq!(sim.eval("EXIT"), Err(Error::InvalidInstruction));
+  }
+
+  #[test]
+  fn run_yielding_test() {
+    let program = lines("@MAIN\nLI $1 1\nLI $2 2\nLI $3 3\nLI $4 4");
+    let mut sim = Simulator::new();
+    sim.load(&program).unwrap();
+    let mut yields = 0;
+    sim
+      .run_yielding(2, |x| {
+        yields += 1;
+        assert_eq!(x.program_counter, 2);
+        false
+      })
+      .unwrap();
+    assert_eq!(yields, 1);
+    assert_eq!(sim.int_registers[1..5], [1, 0, 0, 0]);
+  }
+
+  #[test]
+  fn run_until_register_test() {
+    let program = lines("@MAIN\nLI $2 1\nLI $3 10\n@LOOP\nADD $1 $1 $2\nBLT $1 $3 @LOOP");
+    let mut sim = Simulator::new();
+    sim.load(&program).unwrap();
+    assert_eq!(sim.run_until_register(1, 5), Ok(RunState::Paused));
+    assert_eq!(sim.int_registers[1], 5);
+    assert_eq!(sim.program_counter, 5);
+    assert_eq!(sim.run_until_register(1, -1), Ok(RunState::Halted));
+    assert_eq!(sim.run_until_register(32, 0), Err(Error::OutOfRange));
+  }
+
+  #[test]
+  fn jump_main_test() {
+    let program = lines("@MAIN\nADDI $1 $1 1\nLI $2 3\nBGE $1 $2 @END\nJUMP @MAIN\n@END");
+    let mut sim = Simulator::new();
+    sim.load(&program).unwrap();
+    assert!(sim.run_with_fuel(100).is_ok());
+    assert_eq!(sim.int_registers[1], 3);
+  }
+
+  #[test]
+  fn nop_test() {
+    let mut sim = Simulator::new().with_register_init(7);
+    sim.load(&lines("@MAIN\nNOP")).unwrap();
+    assert_eq!(sim.instructions[1], Instructions::NOP);
+    sim.start().unwrap();
+    let outcome = sim.step_n(2).unwrap();
+    assert!(outcome.halted);
+    assert_eq!(sim.program_counter(), 2);
+    assert!(sim.int_registers.iter().all(|&x| x == 7));
+  }
+
+  #[test]
+  fn steps_test() {
+    let program = lines("@MAIN\nSTEPS $1\nLI $3 0\nSTEPS $2");
+    let mut sim = Simulator::new();
+    sim.load(&program).unwrap();
+    sim.run(false).unwrap();
+    assert_eq!(sim.int_registers[1], 2);
+    assert_eq!(sim.int_registers[2], 4);
+
+    let mut sim = Simulator::new().with_trace(true);
+    sim.load(&program).unwrap();
+    sim.run(false).unwrap();
+    assert_eq!(sim.int_registers[1..3], [2, 4]);
+  }
+
+  #[test]
+  fn run_slice_test() {
+    let program = lines("@MAIN\nLI $1 1\nPRINT $1\nLI $1 2\nPRINT $1\nLI $1 3\nPRINT $1");
+    let mut sim = Simulator::new().with_captured_output();
+    sim.load(&program).unwrap();
+    assert_eq!(sim.run_slice(4), Ok(RunState::Paused));
+    assert_eq!(sim.take_output(), "PRINT: $1: 1\n");
+    let mut output = String::from("PRINT: $1: 1\n");
+    assert_eq!(sim.run_slice(4), Ok(RunState::Halted));
+    output.push_str(&sim.take_output());
+
+    let mut whole = Simulator::new().with_captured_output();
+    whole.load(&program).unwrap();
+    whole.run(false).unwrap();
+    assert_eq!(output, whole.take_output());
+
+    assert_eq!(sim.run_slice(2), Ok(RunState::Paused));
+    assert_eq!(sim.int_registers[1], 1);
+  }
+
+  #[test]
+  fn run_slice_restart_test() {
+    let program = lines("@MAIN\nLI $1 1\nPRINT $1\nLI $1 2\nPRINT $1");
+    let mut sim = Simulator::new().with_captured_output();
+    sim.load(&program).unwrap();
+    assert_eq!(sim.run_slice(3), Ok(RunState::Paused));
+    // Loading more lines starts the next slice from the main label again.
+    sim.load(&lines("LI $2 7\nPRINT $2")).unwrap();
+    assert_eq!(sim.run_slice(10), Ok(RunState::Halted));
+    assert_eq!(
+      sim.take_output(),
+      "PRINT: $1: 1\nPRINT: $1: 1\nPRINT: $1: 2\nPRINT: $2: 7\n"
+    );
+
+    assert_eq!(sim.run_slice(3), Ok(RunState::Paused));
+    assert_eq!(sim.run_slice(2), Ok(RunState::Paused));
+    sim.reset();
+    assert_eq!(sim.run_slice(3), Ok(RunState::Paused));
+    assert_eq!(sim.program_counter, 3);
+
+    assert_eq!(sim.run_slice(1), Ok(RunState::Paused));
+    sim.start().unwrap();
+    assert_eq!(sim.run_slice(1), Ok(RunState::Paused));
+    assert_eq!(sim.program_counter, 1);
+  }
+
+  #[test]
+  fn step_n_test() {
+    let program = lines(
+      "@MAIN\nLI $1 1\nLI $2 2\nLI $3 3\nLI $4 4\nLI $5 5\nLI $6 6\nLI $7 7\nLI $8 8\nLI $9 9",
+    );
+    let mut sim = Simulator::new();
+    sim.load(&program).unwrap();
+    sim.start().unwrap();
+    let outcome = sim.step_n(5).unwrap();
+    assert_eq!(
+      outcome,
+      StepOutcome {
+        executed: 5,
+        halted: false
+      }
+    );
+    assert_eq!(sim.int_registers[4], 4);
+    assert_eq!(sim.int_registers[5], 0);
+    let outcome = sim.step_n(10).unwrap();
+    assert_eq!(
+      outcome,
+      StepOutcome {
+        executed: 5,
+        halted: true
+      }
+    );
+  }
+
+  #[test]
+  fn run_with_fuel_test() {
+    // 3 instructions before the loop, 3 in the first iteration and 2 in the other two.
+    let program = lines("@MAIN\nLI $1 3\nLI $2 1\n@LOOP\nSUB $1 $1 $2\nBNE $1 $0 @LOOP");
+    let mut sim = Simulator::new();
+    sim.load(&program).unwrap();
+    assert_eq!(sim.run_with_fuel(15), Ok(5));
+    assert_eq!(sim.run_with_fuel(10), Ok(0));
+    assert_eq!(sim.run_with_fuel(9), Err(Error::OutOfFuel));
+  }
+
+  #[test]
+  fn run_expecting_test() {
+    let program = lines("@MAIN\nLI $1 4\nPRINT $1\nPRINT $0");
+    let mut sim = Simulator::new();
+    sim.load(&program).unwrap();
+    assert_eq!(sim.run_expecting("PRINT: $1: 4\nPRINT: $0: 0\n"), Ok(()));
+    let err = sim
+      .run_expecting("PRINT: $1: 4\nPRINT: $0: 1\n")
+      .unwrap_err();
+    assert_eq!(
+      err,
+      Error::OutputMismatch {
+        line: 2,
+        expected: Some(String::from("PRINT: $0: 1")),
+        actual: Some(String::from("PRINT: $0: 0")),
+      }
+    );
+    assert_eq!(
+      err.to_string(),
+      "the output differs from the expected one at line 2: expected `PRINT: $0: 1`, got `PRINT: $0: 0`"
+    );
+    let err = sim.run_expecting("PRINT: $1: 4\n").unwrap_err();
+    assert_eq!(
+      err,
+      Error::OutputMismatch {
+        line: 2,
+        expected: None,
+        actual: Some(String::from("PRINT: $0: 0")),
+      }
+    );
+    assert_eq!(
+      err.to_string(),
+      "the output differs from the expected one at line 2: expected the end of the output, got `PRINT: $0: 0`"
+    );
+  }
+
+  #[test]
+  fn current_instruction_test() {
+    let mut sim = Simulator::new();
+    sim
+      .load(&lines("@MAIN\nLI $1 1\nADD $2 $1 $1\nPRINT $2"))
+      .unwrap();
+    sim.start().unwrap();
+    assert_eq!(sim.current_instruction(), Some(&Instructions::LABEL));
+    sim.step_n(2).unwrap();
+    assert_eq!(sim.program_counter(), 2);
+    assert_eq!(sim.current_instruction(), Some(&Instructions::ADD(2, 1, 1)));
+    sim.step_n(2).unwrap();
+    assert_eq!(sim.current_instruction(), None);
+  }
+
+  #[test]
+  fn call_ret_test() {
+    let program = lines(
+      "@MAIN\nLI $2 1\nCALL @INC\nCALL @INC\nPRINT $1\nJUMP @END\n\
+       @INC\nADD $1 $1 $2\nRET\n@END",
+    );
+    let mut sim = Simulator::new().with_captured_output();
+    sim.load(&program).unwrap();
+    sim.run(false).unwrap();
+    assert_eq!(sim.take_output(), "PRINT: $1: 2\n");
+    assert!(sim.call_stack.is_empty());
+  }
+
+  #[test]
+  fn exit_in_subroutine_test() {
+    let program = lines("@MAIN\nCALL @STOP\nLI $1 1\n@STOP\nCALL @DEEPER\n@DEEPER\nEXIT");
+    let mut sim = Simulator::new().with_captured_output();
+    sim.load(&program).unwrap();
+    assert_eq!(sim.run(false), Ok(()));
+    assert_eq!(sim.int_registers[1], 0);
+    assert_eq!(sim.call_stack.len(), 2);
+    assert_eq!(sim.take_output(), "EXIT\n");
+  }
+
+  #[test]
+  fn exit_test() {
+    let program = lines("@MAIN\nLI $1 1\nEXIT\nLI $1 2");
+    let mut sim = Simulator::new().with_captured_output();
+    sim.load(&program).unwrap();
+    for _ in 0..2 {
+      assert_eq!(sim.run(false), Ok(()));
+      assert_eq!(sim.int_registers[1], 1);
+      assert_eq!(sim.take_output(), "EXIT\n");
+    }
+
+    let mut sim = Simulator::new().with_pc_wrap(true);
+    sim.load(&program).unwrap();
+    assert_eq!(sim.run_with_fuel(10), Ok(7));
+    assert_eq!(sim.program_counter, 4);
+  }
+
+  #[test]
+  fn tailcall_loop_test() {
+    let program = lines(
+      "@MAIN\nLI $2 100\nLI $3 1\nCALL @LOOP\nPRINT $1\nJUMP @END\n\
+       @LOOP\nBGE $1 $2 @DONE\nADD $1 $1 $3\nTAILCALL @LOOP\n@DONE\nRET\n@END",
+    );
+    let mut sim = Simulator::new().with_captured_output();
+    sim.load(&program).unwrap();
+    sim.run(false).unwrap();
+    assert_eq!(sim.take_output(), "PRINT: $1: 100\n");
+    assert_eq!(sim.max_call_depth(), 1);
+    assert_eq!(sim.max_stack_depth(), 0);
+  }
+
+  #[test]
+  fn run_without_exit_test() {
+    let mut sim = Simulator::new();
+    sim.load(&lines("@MAIN\nLI $1 1")).unwrap();
+    assert_eq!(sim.run(false), Ok(()));
+    assert_eq!(sim.program_counter, 2);
+  }
+
+  /// The trace goes to stderr, which the test cannot read: it only checks that it is not in the output.
+  #[test]
+  fn debug_trace_not_in_output_test() {
+    let mut sim = Simulator::new().with_captured_output();
+    sim.load(&lines("@MAIN\nLI $1 5\nPRINT $1")).unwrap();
+    sim.run(true).unwrap();
+    assert_eq!(sim.take_output(), "PRINT: $1: 5\n");
+  }
+
+  #[test]
+  fn require_label_use_test() {
+    let program = lines("@MAIN\nLI $1 1\n@UNUSED\nPRINT $1");
+    let mut sim = Simulator::new();
+    assert_eq!(sim.load(&program), Ok(()));
+    let mut sim = Simulator::new().with_require_label_use(true);
+    assert_eq!(sim.load(&program), Err(Error::UnusedLabel));
+
+    let program = lines("@MAIN\nJUMP @END\n@END\nPRINT $1");
+    let mut sim = Simulator::new().with_require_label_use(true);
+    assert_eq!(sim.load(&program), Ok(()));
+  }
+
+  #[test]
+  fn step_diff_test() {
+    let mut sim = Simulator::new();
+    sim
+      .load(&lines("@MAIN\nLI $1 2\nADD $3 $1 $1\nPUSH $3"))
+      .unwrap();
+    sim.start().unwrap();
+    sim.step_n(2).unwrap();
+    let diff = sim.step_diff().unwrap();
+    assert_eq!(
+      diff,
+      StepDiff {
+        pc: (2, 3),
+        registers: vec![(3, 0, 4)],
+        stack_depth: None,
+      }
+    );
+    let diff = sim.step_diff().unwrap();
+    assert_eq!(diff.registers, vec![]);
+    assert_eq!(diff.stack_depth, Some((0, 1)));
+  }
+
+  #[test]
+  fn line_too_long_test() {
+    let mut program = lines("@MAIN\nLI $1 1");
+    program.push(format!("// {}", "x".repeat(DEFAULT_MAX_LINE_LENGTH)));
+    let mut sim = Simulator::new();
+    assert_eq!(sim.load(&program), Err(Error::LineTooLong { line: 3 }));
+
+    let mut sim = Simulator::new().with_max_line_length(8);
+    assert_eq!(
+      sim.load(&lines("@MAIN\nLI $1 1\nADD $1 $1 $1")),
+      Err(Error::LineTooLong { line: 3 })
+    );
+  }
+
+  #[test]
+  fn null_output_test() {
+    let program = lines(
+      "@MAIN\nLI $2 1000\nLI $3 1\n@LOOP\nPRINT $1\nPRINTNN $1\nPRINTREGS\nADD $1 $1 $3\nBLT $1 $2 @LOOP",
+    );
+    let mut sim = Simulator::new().with_null_output();
+    sim.load(&program).unwrap();
+    assert_eq!(sim.run(false), Ok(()));
+    assert_eq!(sim.int_registers[1], 1000);
+    assert_eq!(sim.take_output(), "");
+  }
+
+  #[test]
+  fn output_channel_test() {
+    let program = lines("@MAIN\nLI $1 7\nPRINT $1\nPRINTLN\nPRINT $2");
+    let (sender, receiver) = channel();
+    let mut sim = Simulator::new().with_output_channel(sender);
+    sim.load(&program).unwrap();
+    let handle = std::thread::spawn(move || receiver.iter().collect::<Vec<PrintEvent>>());
+    sim.run(false).unwrap();
+    drop(sim);
+    let events = handle.join().unwrap();
+    assert_eq!(
+      events,
+      vec![
+        PrintEvent::Value {
+          register: 1,
+          value: 7
+        },
+        PrintEvent::Text(String::from("\n")),
+        PrintEvent::Value {
+          register: 2,
+          value: 0
+        },
+      ]
+    );
+
+    let (sender, receiver) = channel();
+    drop(receiver);
+    let mut sim = Simulator::new().with_output_channel(sender);
+    sim.load(&program).unwrap();
+    assert_eq!(sim.run(false), Err(Error::OutputClosed));
+  }
+
+  #[test]
+  fn jump_targets_test() {
+    let program =
+      lines("@MAIN\nLI $2 3\n@LOOP\nADD $1 $1 $2\nBLT $1 $2 @LOOP\nJUMP @END\n@END\nPRINT $1");
+    let mut sim = Simulator::new();
+    sim.load(&program).unwrap();
+    assert_eq!(sim.jump_targets(), vec![(4, 2), (4, 5), (5, 6)]);
+  }
+
+  #[test]
+  fn jump_targets_skip_test() {
+    let program = lines(
+      "@MAIN
+SKIPEQ $1 $2
+LI $1 1
+PRINT $1
+SKIPNE $1 $2
+PRINT $1",
+    );
+    let mut sim = Simulator::new();
+    sim.load(&program).unwrap();
+    assert_eq!(sim.jump_targets(), vec![(1, 2), (1, 3), (4, 5)]);
+  }
+
+  #[test]
+  fn verbose_not_in_output_test() {
+    let program = lines("@MAIN\nPRINT $1");
+    let mut sim = Simulator::new().with_captured_output().with_verbose(true);
+    sim.load(&program).unwrap();
+    sim.run(false).unwrap();
+    assert_eq!(sim.take_output(), "PRINT: $1: 0\n");
+  }
+
+  #[test]
+  fn max_stack_depth_test() {
+    let program =
+      lines("@MAIN\nPUSH $1\nPUSH $1\nPUSH $1\nPOP $2\nPOP $2\nPUSH $1\nPOP $2\nPOP $2");
+    let mut sim = Simulator::new();
+    sim.load(&program).unwrap();
+    sim.run(false).unwrap();
+    assert_eq!(sim.max_stack_depth(), 3);
+    assert!(sim.stack.is_empty());
+  }
+
+  #[test]
+  fn pc_wrap_test() {
+    let program = lines("@MAIN\nLI $1 1\nADD $2 $2 $1");
+    let mut sim = Simulator::new().with_pc_wrap(true);
+    sim.load(&program).unwrap();
+    assert_eq!(sim.run_with_fuel(7), Err(Error::OutOfFuel));
+    assert_eq!(sim.int_registers[2], 2);
+    assert_eq!(sim.program_counter, 1);
+    assert_eq!(sim.run(false), Err(Error::UnboundedWrap));
+    assert_eq!(sim.run_expecting(""), Err(Error::UnboundedWrap));
+    assert_eq!(sim.run_until_register(2, -1), Err(Error::UnboundedWrap));
+    assert_eq!(sim.run_yielding(0, |_| true), Err(Error::UnboundedWrap));
+    assert_eq!(sim.run_yielding(5, |_| false), Ok(()));
+
+    let mut sim = Simulator::new();
+    sim.load(&program).unwrap();
+    assert_eq!(sim.run_with_fuel(7), Ok(4));
+    assert_eq!(sim.int_registers[2], 1);
+  }
+
+  #[test]
+  fn execute_test() {
+    let result = Simulator::execute(&lines("@MAIN\nLI $1 4\nLI $2 5\nADD $3 $1 $2\nPRINT $3"));
+    assert_eq!(result.output, "PRINT: $3: 9\n");
+    assert_eq!(result.final_registers.len(), INT_REGISTER_COUNT);
+    assert_eq!(&result.final_registers[1..4], &[4, 5, 9]);
+    assert_eq!(result.steps, 5);
+    assert_eq!(result.error, None);
+
+    let result = Simulator::execute(&lines("@MAIN\nLI $1 1\nLI $2 0\nDIV $3 $1 $2"));
+    assert_eq!(result.steps, 4);
+    assert_eq!(result.error, Some(Error::DivisionByZero));
+  }
+
+  #[test]
+  fn register_init_test() {
+    let mut sim = Simulator::new().with_register_init(0xDEAD);
+    assert!(sim.int_registers.iter().all(|&x| x == 0xDEAD));
+    sim.load(&lines("@MAIN\nLI $1 3")).unwrap();
+    sim.run(false).unwrap();
+    assert_eq!(sim.int_registers[1], 3);
+    sim.reset();
+    assert!(sim.int_registers.iter().all(|&x| x == 0xDEAD));
+    assert_eq!(sim.program_counter, 0);
+  }
+
+  #[test]
+  fn linear_run_test() {
+    let program = lines("@MAIN\nLI $1 6\nLI $2 7\nMUL $3 $1 $2\nPUSH $3\nPOP $4\nPRINT $4");
+    let mut fast = Simulator::new().with_captured_output();
+    fast.load(&program).unwrap();
+    assert!(fast.is_linear);
+    fast.run(false).unwrap();
+    let mut general = Simulator::new().with_captured_output().with_trace(true);
+    general.load(&program).unwrap();
+    general.run(false).unwrap();
+    assert_eq!(fast.take_output(), general.take_output());
+    assert_eq!(fast.int_registers, general.int_registers);
+    assert_eq!(fast.program_counter, general.program_counter);
+
+    let mut failing = Simulator::new();
+    failing
+      .load(&lines("@MAIN\nLI $1 1\nDIV $2 $1 $0\nLI $3 3"))
+      .unwrap();
+    assert_eq!(failing.run(false), Err(Error::DivisionByZero));
+    assert_eq!(failing.program_counter, 2);
+    assert_eq!(failing.instructions.len(), 4);
+
+    let mut sim = Simulator::new();
+    sim
+      .load(&lines("@MAIN\nLI $1 1\nSKIPEQ $1 $1\nLI $1 2"))
+      .unwrap();
+    assert!(!sim.is_linear);
+  }
+
+  #[test]
+  fn load_preserving_comments_test() {
+    let program = lines("// header\n@MAIN\n\nLI $1 3 // three\n  // body");
+    let mut sim = Simulator::new();
+    sim.load_preserving_comments(&program).unwrap();
+    assert_eq!(sim.instructions.len(), 5);
+    assert_eq!(sim.instructions[0], Instructions::SKIP);
+    assert_eq!(sim.comment(0), Some("header"));
+    assert_eq!(sim.instructions[2], Instructions::SKIP);
+    assert_eq!(sim.comment(2), None);
+    assert_eq!(sim.comment(3), Some("three"));
+    assert_eq!(sim.instructions[4], Instructions::SKIP);
+    assert_eq!(sim.comment(4), Some("body"));
+    assert_eq!(sim.labels["@MAIN"], 1);
+  }
+
+  #[test]
+  fn expected_output_test() {
+    let program = lines(
+      "//! OUTPUT: PRINT: $1: 42\n@MAIN\nLI $1 42\nPRINT $1\n//! OUTPUT: PRINT: $0: 0\nPRINT $0",
+    );
+    let mut sim = Simulator::new();
+    sim.load(&program).unwrap();
+    assert_eq!(sim.expected_output(), "PRINT: $1: 42\nPRINT: $0: 0\n");
+    let expected = sim.expected_output();
+    assert_eq!(sim.run_expecting(&expected), Ok(()));
+  }
+
+  #[test]
+  fn captured_output_test() {
+    let program = lines("@MAIN\nLI $1 4\nPRINT $1\nLI $2 -2\nPRINT $2");
+    let mut sim = Simulator::new().with_captured_output();
+    sim.load(&program).unwrap();
+    sim.run(false).unwrap();
+    assert_eq!(sim.take_output(), "PRINT: $1: 4\nPRINT: $2: -2\n");
+    assert_eq!(sim.take_output(), "");
+  }
+
+  #[test]
+  fn stack_balance_check_test() {
+    let program = lines("@MAIN\nLI $1 1\nPUSH $1\nPUSH $1\nPOP $2");
+    let mut sim = Simulator::new();
+    sim.load(&program).unwrap();
+    assert_eq!(sim.run(false), Ok(()));
+
+    let mut sim = Simulator::new().with_stack_balance_check(true);
+    sim.load(&program).unwrap();
+    assert_eq!(sim.run(false), Err(Error::StackImbalance));
+
+    let program = lines("@MAIN\nCALL @SUB\n@SUB\nEXIT");
+    let mut sim = Simulator::new()
+      .with_stack_balance_check(true)
+      .with_captured_output();
+    sim.load(&program).unwrap();
+    assert_eq!(sim.run(false), Err(Error::StackImbalance));
+  }
+
+  #[test]
+  fn dependencies_test() {
+    let program = lines("@MAIN\nLI $1 2\nLI $2 3\nADD $3 $1 $2\nMUL $3 $3 $3\nPRINT $3");
+    let mut sim = Simulator::new();
+    sim.load(&program).unwrap();
+    assert_eq!(sim.dependencies(), vec![(1, 3), (2, 3), (3, 4), (4, 5)]);
+  }
+
+  #[test]
+  fn dependencies_multiple_destinations_test() {
+    let program = lines(
+      "@MAIN\nLI $1 2\nLI $2 3\nSWAP $1 $2\nPRINT $1\nPRINT $2\nVADD $4 $1 $1 2\nPRINT $5\nREADALL $6 2\nPRINT $7",
+    );
+    let mut sim = Simulator::new();
+    sim.load(&program).unwrap();
+    assert_eq!(
+      sim.dependencies(),
+      vec![(1, 3), (2, 3), (3, 4), (3, 5), (3, 6), (6, 7), (8, 9)]
+    );
+  }
+
+  #[test]
+  fn destinations_test() {
+    assert_eq!(Instructions::SWAP(1, 2).destinations(), vec![1, 2]);
+    assert_eq!(Instructions::VADD(4, 1, 8, 3).destinations(), vec![4, 5, 6]);
+    assert_eq!(Instructions::READALL(6, 2).destinations(), vec![6, 7]);
+    assert_eq!(Instructions::ADD(3, 1, 2).destinations(), vec![3]);
+    assert_eq!(Instructions::PRINT(3).destinations(), Vec::<usize>::new());
+    assert_eq!(Instructions::VADD(4, 1, 8, 2).sources(), vec![1, 2, 8, 9]);
+  }
+
+  #[test]
+  fn checkpoint_test() {
+    let program = lines("@MAIN\nLI $1 3\nPUSH $1\nLI $2 4\nPUSH $2\nPOP $3");
+    let mut sim = Simulator::new();
+    sim.load(&program).unwrap();
+    sim.program_counter = 0;
+    sim.step(false).unwrap();
+    sim.step(false).unwrap();
+    sim.step(false).unwrap();
+    let cp = sim.checkpoint();
+    while sim.program_counter < sim.instructions.len() {
+      sim.step(false).unwrap();
+    }
+    assert_ne!(sim.checkpoint(), cp);
+    sim.restore(&cp);
+    assert_eq!(sim.checkpoint(), cp);
+    assert_eq!(sim.program_counter, 3);
+    assert_eq!(sim.int_registers[2], 0);
+    assert_eq!(sim.stack.len(), 1);
+
+    let program = lines("@MAIN\nLI $1 1\nLI $2 2\nEXIT");
+    let mut sim = Simulator::new().with_captured_output();
+    sim.load(&program).unwrap();
+    sim.start().unwrap();
+    sim.step(false).unwrap();
+    let cp = sim.checkpoint();
+    while sim.program_counter < sim.instructions.len() {
+      sim.step(false).unwrap();
+    }
+    sim.restore(&cp);
+    sim.step(false).unwrap();
+    assert_eq!(sim.program_counter, 2);
+    assert_eq!(sim.int_registers[1], 1);
+  }
+
+  #[test]
+  fn label_order_test() {
+    let program = lines("@MAIN\n@ZETA\n@ALPHA\n@MID\nLI $1 1\n@BETA\nJUMP @MID");
+    let outputs: Vec<(Vec<String>, String)> = (0..2)
+      .map(|_| {
+        let mut sim = Simulator::new();
+        sim.load(&program).unwrap();
+        (sim.unused_labels(), sim.disassemble())
+      })
+      .collect();
+    assert_eq!(outputs[0], outputs[1]);
+    assert_eq!(outputs[0].0, vec!["@ZETA", "@ALPHA", "@BETA"]);
+  }
+
+  #[test]
+  fn insert_label_test() {
+    let mut sim = Simulator::new();
+    sim
+      .load(&lines("@MAIN\nJUMP @END\nLI $1 1\nLI $2 2"))
+      .unwrap();
+    assert_eq!(sim.insert_label("main", 2), Err(Error::DuplicateLabel));
+    assert_eq!(sim.insert_label("@END", 4), Err(Error::OutOfRange));
+    sim.insert_label("end", 3).unwrap();
+    assert_eq!(sim.instructions[3], Instructions::LABEL);
+    assert_eq!(sim.instructions[4], Instructions::LI(2, 2));
+    sim.run(false).unwrap();
+    assert_eq!(sim.int_registers[1..3], [0, 2]);
+
+    let mut sim = Simulator::new();
+    sim
+      .load(&lines("@MAIN\nJUMP @MIDDLE\nLI $1 1\n@END\nLI $2 2"))
+      .unwrap();
+    sim.insert_label("middle", 2).unwrap();
+    assert_eq!(sim.labels["@END"], 4);
+    sim.run(false).unwrap();
+    assert_eq!(sim.int_registers[1..3], [1, 2]);
+  }
+
+  #[test]
+  fn validate_labels_test() {
+    let mut sim = Simulator::new();
+    sim.load(&lines("@MAIN\nLI $1 1")).unwrap();
+    assert_eq!(sim.validate_labels(), Ok(()));
+    sim.labels.insert(String::from("@END"), 2);
+    assert_eq!(
+      sim.validate_labels(),
+      Err(Error::LabelOutOfRange { index: 2 })
+    );
+    let err = sim.validate_labels().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Parse);
+    assert_eq!(err.code(), "label-out-of-range");
+  }
+
+  #[test]
+  fn unused_labels_test() {
+    let program = lines("@MAIN\n@LOOP\nLI $1 1\n@DEAD\nBEQ $1 $0 @LOOP");
+    let mut sim = Simulator::new();
+    sim.load(&program).unwrap();
+    assert_eq!(sim.unused_labels(), vec![String::from("@DEAD")]);
+  }
+
+  #[test]
+  fn trace_csv_test() {
+    let program = lines("@MAIN\nLI $2 3\nADD $1 $2 $2");
+    let mut sim = Simulator::new().with_trace(true);
+    sim.load(&program).unwrap();
+    sim.run(false).unwrap();
+    let csv = sim.trace_csv();
+    let rows: Vec<&str> = csv.lines().collect();
+    assert_eq!(rows[0], "step,pc,mnemonic,changed_reg,new_value");
+    assert_eq!(rows[3], "2,2,ADD,1,6");
+  }
+
+  #[test]
+  fn trace_out_of_range_test() {
+    let program = lines("@MAIN\nLI $1 1");
+    let mut sim = Simulator::new()
+      .with_trace(true)
+      .with_error_policy(ErrorPolicy::Continue);
+    sim.load(&program).unwrap();
+    sim.start().unwrap();
+    sim.program_counter = 5;
+    sim.step(true).unwrap();
+    assert_eq!(sim.runtime_warnings(), [(5, Error::OutOfRange)]);
+    assert_eq!(sim.trace_csv(), "step,pc,mnemonic,changed_reg,new_value\n");
+  }
+} // mod simulator_test
@@ -0,0 +1,221 @@
+//! Author: TheLazyFerret (https://github.com/TheLazyFerret)
+//! Copyright (c) 2025 TheLazyFerret
+//! Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//!
+//! line based diagnostics (for editors) related module
+
+use crate::simulator::{
+  DEFAULT_MAX_LINE_LENGTH, Error, Simulator,
+  lint::{Severity, Warning},
+  parser::{check_line_length, preprocess_numbered_lines, process_lines},
+};
+
+/// Position of the code of a source line: the line starts at 1, the columns (in bytes) at 0.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+  pub line: usize,
+  pub start: usize,
+  pub end: usize,
+}
+
+/// A problem found in the source, with the range it refers to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+  pub range: Span,
+  pub severity: Severity,
+  pub code: &'static str,
+  pub message: String,
+}
+
+/// Returns the span of the code in the line, without the indentation and the trailing comment.
+fn code_span(line: usize, text: &str) -> Span {
+  let code = text.split_once("//").map_or(text, |x| x.0).trim_end();
+  let start = code.len() - code.trim_start().len();
+  Span {
+    line,
+    start,
+    end: code.len(),
+  }
+}
+
+impl Diagnostic {
+  fn from_error(range: Span, error: Error) -> Self {
+    Diagnostic {
+      range,
+      severity: Severity::Error,
+      code: error.code(),
+      message: error.to_string(),
+    }
+  }
+
+  fn from_warning(range: Span, warning: &Warning) -> Self {
+    Diagnostic {
+      range,
+      severity: warning.severity(),
+      code: warning.code(),
+      message: warning.to_string(),
+    }
+  }
+}
+
+impl Simulator {
+  /// Returns every parse error and lint warning of the source, sorted by line, without running it.
+  /// Unlike `load`, the parsing goes on after a wrong line, so all of them are reported.
+  pub fn diagnose(lines: &[String]) -> Vec<Diagnostic> {
+    let mut sim = Simulator::new();
+    let mut diagnostics = Vec::new();
+    // Source line of each instruction, to place the warnings about instructions and labels.
+    let mut spans: Vec<Span> = Vec::new();
+    // Like `load`, the length is checked on the raw lines, comments included.
+    let mut too_long = Vec::new();
+    for (index, text) in lines.iter().enumerate() {
+      if check_line_length(std::slice::from_ref(text), DEFAULT_MAX_LINE_LENGTH).is_err() {
+        let line = index + 1;
+        let span = Span {
+          line,
+          start: 0,
+          end: text.len(),
+        };
+        diagnostics.push(Diagnostic::from_error(span, Error::LineTooLong { line }));
+        too_long.push(index);
+      }
+    }
+    for (index, text) in preprocess_numbered_lines(lines) {
+      if too_long.contains(&index) {
+        continue;
+      }
+      let line = index + 1;
+      let span = code_span(line, &text);
+      match process_lines(std::slice::from_ref(&text), &mut sim) {
+        Ok(()) => spans.resize(sim.instructions.len(), span),
+        Err(err) => diagnostics.push(Diagnostic::from_error(span, err)),
+      }
+    }
+
+    let label_span = |name: &str| sim.labels.get(name).and_then(|&x| spans.get(x)).copied();
+    let main = label_span("@MAIN");
+    if main.is_none() {
+      let span = Span {
+        line: 1,
+        start: 0,
+        end: 0,
+      };
+      diagnostics.push(Diagnostic::from_error(span, Error::MainNotFound));
+    }
+    for warning in sim.lint() {
+      let span = match &warning {
+        Warning::MnemonicLabel(name) => label_span(name),
+        Warning::EmptyMain => main,
+        Warning::MainReentry(index) => spans.get(*index).copied(),
+      };
+      if let Some(span) = span {
+        diagnostics.push(Diagnostic::from_warning(span, &warning));
+      }
+    }
+    for name in sim.unused_labels() {
+      if let Some(range) = label_span(&name) {
+        diagnostics.push(Diagnostic {
+          range,
+          severity: Severity::Warning,
+          code: Error::UnusedLabel.code(),
+          message: format!("the label {name} is never referenced by any jump"),
+        });
+      }
+    }
+    diagnostics.sort_by_key(|x| (x.range.line, x.range.start));
+    diagnostics
+  }
+}
+
+#[cfg(test)]
+mod diagnostic_test {
+  use crate::simulator::{
+    DEFAULT_MAX_LINE_LENGTH, Error, Simulator,
+    diagnostic::{Diagnostic, Span},
+    lint::Severity,
+  };
+
+  #[test]
+  fn diagnose_test() {
+    let lines: Vec<String> = "@MAIN\nLI $1 1\n  FOO $1 // typo\n@UNUSED\nPRINT $1"
+      .lines()
+      .map(String::from)
+      .collect();
+    let expected = vec![
+      Diagnostic {
+        range: Span {
+          line: 3,
+          start: 2,
+          end: 8,
+        },
+        severity: Severity::Error,
+        code: "invalid-instruction",
+        message: String::from("the instruction is not valid, or doesn't exist"),
+      },
+      Diagnostic {
+        range: Span {
+          line: 4,
+          start: 0,
+          end: 7,
+        },
+        severity: Severity::Warning,
+        code: "unused-label",
+        message: String::from("the label @UNUSED is never referenced by any jump"),
+      },
+    ];
+    assert_eq!(Simulator::diagnose(&lines), expected);
+  }
+
+  #[test]
+  fn diagnose_main_not_found_test() {
+    let lines = vec![String::from("LI $1 1")];
+    let diagnostics = Simulator::diagnose(&lines);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, "main-not-found");
+  }
+
+  #[test]
+  fn diagnose_bom_crlf_test() {
+    let lines: Vec<String> = vec![
+      String::from("\u{feff}@MAIN\r"),
+      String::from("LI $1 1 // comment\r"),
+      String::from("PRINT $1 junk\r"),
+    ];
+    let diagnostics = Simulator::diagnose(&lines);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, "unexpected-token");
+    assert_eq!(
+      diagnostics[0].range,
+      Span {
+        line: 3,
+        start: 0,
+        end: 13,
+      }
+    );
+  }
+
+  #[test]
+  fn diagnose_long_comment_test() {
+    let comment = format!("// {}", "x".repeat(DEFAULT_MAX_LINE_LENGTH));
+    let lines = vec![
+      String::from("@MAIN"),
+      comment.clone(),
+      String::from("LI $1 1"),
+    ];
+    assert_eq!(
+      Simulator::new().load(&lines),
+      Err(Error::LineTooLong { line: 2 })
+    );
+    let diagnostics = Simulator::diagnose(&lines);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, "line-too-long");
+    assert_eq!(
+      diagnostics[0].range,
+      Span {
+        line: 2,
+        start: 0,
+        end: comment.len(),
+      }
+    );
+  }
+} // mod diagnostic_test
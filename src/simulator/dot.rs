@@ -0,0 +1,132 @@
+//! Author: TheLazyFerret (https://github.com/TheLazyFerret)
+//! Copyright (c) 2025 TheLazyFerret
+//! Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//!
+//! control flow graph (Graphviz DOT) related module
+
+use std::collections::BTreeSet;
+
+use crate::simulator::{Instructions, Simulator};
+
+impl Simulator {
+  /// Returns the indexes of the instructions that may run after the one at index,
+  /// taking those of the instructions that change the flow from the edges of `jump_targets`.
+  fn successors(&self, edges: &[(usize, usize)], index: usize) -> Vec<usize> {
+    let instruction = &self.instructions[index];
+    if instruction.changes_flow() {
+      let start = edges.partition_point(|(from, _)| *from < index);
+      edges[start..]
+        .iter()
+        .take_while(|(from, _)| *from == index)
+        .map(|(_, to)| *to)
+        .collect()
+    } else if *instruction == Instructions::EXIT || index + 1 >= self.instructions.len() {
+      Vec::new()
+    } else {
+      vec![index + 1]
+    }
+  }
+
+  /// Returns the control flow graph in Graphviz DOT format.
+  /// Each node is a basic block, named after its label (or `B` and its index),
+  /// and labeled with its first instruction.
+  pub fn to_dot(&self) -> String {
+    let len = self.instructions.len();
+    let edges = self.jump_targets();
+    let mut leaders = BTreeSet::new();
+    if len > 0 {
+      leaders.insert(0);
+    }
+    for (index, _) in self.sorted_labels() {
+      leaders.insert(index);
+    }
+    for (index, instruction) in self.instructions.iter().enumerate() {
+      if instruction.changes_flow() || *instruction == Instructions::EXIT {
+        leaders.extend(self.successors(&edges, index));
+        if index + 1 < len {
+          leaders.insert(index + 1);
+        }
+      }
+    }
+    let leaders: Vec<usize> = leaders.into_iter().filter(|&x| x < len).collect();
+    let labels = self.sorted_labels();
+    let name = |leader: usize| match labels.iter().find(|(index, _)| *index == leader) {
+      Some((_, label)) => label.to_string(),
+      None => format!("B{leader}"),
+    };
+
+    let mut output = String::from("digraph cfg {\n");
+    for (n, &leader) in leaders.iter().enumerate() {
+      let end = leaders.get(n + 1).copied().unwrap_or(len);
+      let first = self.instructions[leader..end]
+        .iter()
+        .find(|x| **x != Instructions::LABEL)
+        .map(|x| x.to_string())
+        .unwrap_or_default();
+      output.push_str(&format!(
+        "  \"{}\" [label=\"{}\\n{}\"];\n",
+        name(leader),
+        name(leader),
+        first
+      ));
+      let mut targets: Vec<usize> = self.successors(&edges, end - 1);
+      targets.dedup();
+      for target in targets {
+        output.push_str(&format!(
+          "  \"{}\" -> \"{}\";\n",
+          name(leader),
+          name(target)
+        ));
+      }
+    }
+    output.push_str("}\n");
+    output
+  }
+}
+
+#[cfg(test)]
+mod dot_test {
+  use crate::simulator::Simulator;
+
+  #[test]
+  fn to_dot_test() {
+    let program: Vec<String> = "@MAIN\nLI $2 3\n@LOOP\nADDI $1 $1 1\nBLT $1 $2 @LOOP\nPRINT $1"
+      .lines()
+      .map(String::from)
+      .collect();
+    let mut sim = Simulator::new();
+    sim.load(&program).unwrap();
+    let expected = [
+      r#"digraph cfg {"#,
+      r#"  "@MAIN" [label="@MAIN\nLI $2 3"];"#,
+      r#"  "@MAIN" -> "@LOOP";"#,
+      r#"  "@LOOP" [label="@LOOP\nADDI $1 $1 1"];"#,
+      r#"  "@LOOP" -> "@LOOP";"#,
+      r#"  "@LOOP" -> "B5";"#,
+      r#"  "B5" [label="B5\nPRINT $1"];"#,
+      r#"}"#,
+    ];
+    assert_eq!(sim.to_dot().lines().collect::<Vec<&str>>(), expected);
+  }
+
+  #[test]
+  fn to_dot_skip_test() {
+    let program: Vec<String> = "@MAIN\nSKIPEQ $1 $2\nLI $1 1\nPRINT $1"
+      .lines()
+      .map(String::from)
+      .collect();
+    let mut sim = Simulator::new();
+    sim.load(&program).unwrap();
+    let expected = [
+      r#"digraph cfg {"#,
+      r#"  "@MAIN" [label="@MAIN\nSKIPEQ $1 $2"];"#,
+      r#"  "@MAIN" -> "B2";"#,
+      r#"  "@MAIN" -> "B3";"#,
+      r#"  "B2" [label="B2\nLI $1 1"];"#,
+      r#"  "B2" -> "B3";"#,
+      r#"  "B3" [label="B3\nPRINT $1"];"#,
+      r#"}"#,
+    ];
+    assert_eq!(sim.to_dot().lines().collect::<Vec<&str>>(), expected);
+  }
+} // mod dot_test
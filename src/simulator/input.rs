@@ -0,0 +1,54 @@
+//! Author: TheLazyFerret (https://github.com/TheLazyFerret)
+//! Copyright (c) 2025 TheLazyFerret
+//! Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//!
+//! input source related module
+
+use std::{collections::VecDeque, io::stdin};
+
+use crate::simulator::Error;
+
+/// Where the program reads its input from.
+#[derive(Debug)]
+pub struct Input {
+  from_stdin: bool,
+  tokens: VecDeque<String>,
+}
+
+impl Default for Input {
+  /// Reads from the standard input.
+  fn default() -> Self {
+    Input {
+      from_stdin: true,
+      tokens: VecDeque::new(),
+    }
+  }
+}
+
+impl Input {
+  /// Creates an input that reads the whitespace separated tokens of the text.
+  pub fn from_text(text: &str) -> Self {
+    Input {
+      from_stdin: false,
+      tokens: text.split_whitespace().map(|x| x.to_string()).collect(),
+    }
+  }
+
+  /// Returns the next integer of the input, read by the instruction at pc.
+  pub fn next_integer(&mut self, pc: usize) -> Result<i32, Error> {
+    while self.tokens.is_empty() {
+      let mut line = String::new();
+      if !self.from_stdin
+        || stdin()
+          .read_line(&mut line)
+          .map_err(|_| Error::InputFailed { pc })?
+          == 0
+      {
+        return Err(Error::EndOfInput);
+      }
+      self.tokens = line.split_whitespace().map(|x| x.to_string()).collect();
+    }
+    let token = self.tokens.pop_front().expect("tokens is not empty");
+    token.parse().map_err(|_| Error::InvalidInput)
+  }
+}
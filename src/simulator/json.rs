@@ -0,0 +1,107 @@
+//! Author: TheLazyFerret (https://github.com/TheLazyFerret)
+//! Copyright (c) 2025 TheLazyFerret
+//! Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//!
+//! JSON program format related module
+
+use serde::{Deserialize, Serialize};
+
+use crate::simulator::{Error, Instructions, Simulator, parser::normalize_label};
+
+/// Each element of a JSON program: a label definition or an instruction.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum Entry {
+  Label { label: String },
+  Instruction(Instructions),
+}
+
+/// Returns the instruction with its jump label, if any, in the canonical form.
+fn normalize_target(mut instruction: Instructions) -> Instructions {
+  if let Some(a) = instruction.target_mut() {
+    *a = normalize_label(a);
+  }
+  instruction
+}
+
+impl Simulator {
+  /// Loads a program from a JSON array, bypassing the text parser.
+  /// Label names are normalized like in the text format, in jumps too (`main` is `@MAIN`).
+  /// e.g. `[{"label":"@MAIN"},{"op":"ADD","args":[1,2,3]}]`
+  pub fn load_json(&mut self, json: &str) -> Result<(), Error> {
+    let entries: Vec<Entry> = serde_json::from_str(json).map_err(|_| Error::InvalidInstruction)?;
+    for entry in entries {
+      match entry {
+        Entry::Label { label } => {
+          self
+            .labels
+            .insert(normalize_label(&label), self.instructions.len());
+          self.instructions.push(Instructions::LABEL);
+        }
+        Entry::Instruction(Instructions::LABEL) => return Err(Error::InvalidInstruction),
+        Entry::Instruction(instruction) => self.instructions.push(normalize_target(instruction)),
+      }
+    }
+    self.after_load()
+  }
+
+  /// Returns the loaded program as a JSON array accepted by `load_json`.
+  pub fn to_json(&self) -> String {
+    let labels = self.sorted_labels();
+    let mut entries = Vec::with_capacity(self.instructions.len());
+    for (index, instruction) in self.instructions.iter().enumerate() {
+      if *instruction == Instructions::LABEL {
+        for (_, name) in labels.iter().filter(|x| x.0 == index) {
+          entries.push(Entry::Label {
+            label: name.to_string(),
+          });
+        }
+      } else {
+        entries.push(Entry::Instruction(instruction.clone()));
+      }
+    }
+    serde_json::to_string(&entries).expect("error serializing")
+  }
+}
+
+#[cfg(test)]
+mod json_test {
+  use crate::simulator::{Instructions, Simulator};
+
+  #[test]
+  fn load_json_test() {
+    let mut sim = Simulator::new();
+    sim
+      .load_json(r#"[{"label":"@MAIN"},{"op":"ADD","args":[1,2,3]},{"op":"EXIT"}]"#)
+      .unwrap();
+    assert_eq!(sim.labels["@MAIN"], 0);
+    assert_eq!(sim.instructions[1], Instructions::ADD(1, 2, 3));
+    assert_eq!(sim.instructions[2], Instructions::EXIT);
+
+    let mut sim = Simulator::new();
+    sim
+      .load_json(r#"[{"label":"main"},{"op":"JUMP","args":"@end"},{"label":"@End"}]"#)
+      .unwrap();
+    assert_eq!(sim.labels["@MAIN"], 0);
+    assert_eq!(sim.labels["@END"], 2);
+    assert_eq!(
+      sim.instructions[1],
+      Instructions::JUMP(String::from("@END"))
+    );
+    sim.run(false).unwrap();
+  }
+
+  #[test]
+  fn json_round_trip_test() {
+    let lines: Vec<String> = "@MAIN\nLI $1 -3\n@LOOP\nADD $2 $2 $1\nBLT $2 $0 @LOOP\nPRINT $2"
+      .lines()
+      .map(|x| x.to_string())
+      .collect();
+    let mut text = Simulator::new();
+    text.load(&lines).unwrap();
+    let mut json = Simulator::new();
+    json.load_json(&text.to_json()).unwrap();
+    assert_eq!(json.instructions, text.instructions);
+    assert_eq!(json.labels, text.labels);
+  }
+} // mod json_test
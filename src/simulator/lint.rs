@@ -0,0 +1,153 @@
+//! Author: TheLazyFerret (https://github.com/TheLazyFerret)
+//! Copyright (c) 2025 TheLazyFerret
+//! Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//!
+//! static analysis (lint) related module
+
+use std::fmt;
+
+use crate::simulator::{Instructions, Simulator, parser::SUPPORTED_INSTRUCTIONS};
+
+/// How serious a warning is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+  Info,
+  Warning,
+  Error,
+}
+
+/// Enum representing the likely bugs found in a loaded program.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Warning {
+  MnemonicLabel(String), // Label named like an instruction
+  EmptyMain,             // No instruction to execute after the main label
+  MainReentry(usize),    // Jump to the main label at the given instruction, restarting the program
+}
+
+impl Warning {
+  /// Returns how serious the warning is.
+  pub fn severity(&self) -> Severity {
+    match self {
+      Warning::MnemonicLabel(_) => Severity::Warning,
+      Warning::EmptyMain => Severity::Error,
+      Warning::MainReentry(_) => Severity::Info,
+    }
+  }
+
+  /// Returns a stable identifier of the kind of warning, for tooling.
+  pub fn code(&self) -> &'static str {
+    match self {
+      Warning::MnemonicLabel(_) => "mnemonic-label",
+      Warning::EmptyMain => "empty-main",
+      Warning::MainReentry(_) => "main-reentry",
+    }
+  }
+} // impl Warning
+
+/// trait for verbose warnings.
+impl fmt::Display for Warning {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Warning::MnemonicLabel(a) => write!(f, "the label {a} has the name of an instruction"),
+      Warning::EmptyMain => {
+        f.write_str("there are no instructions to execute after the main label")
+      }
+      Warning::MainReentry(a) => {
+        write!(
+          f,
+          "the instruction {a} jumps to the main label, restarting the program"
+        )
+      }
+    }
+  }
+}
+
+impl Simulator {
+  /// Returns the warnings found in the loaded program, without running it.
+  pub fn lint(&self) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    for (_, name) in self.sorted_labels() {
+      let mnemonic = name.trim_start_matches('@').to_uppercase();
+      if SUPPORTED_INSTRUCTIONS.contains(&mnemonic.as_str()) {
+        warnings.push(Warning::MnemonicLabel(name.to_owned()));
+      }
+    }
+    if let Some(main) = self.labels.get("@MAIN")
+      && self.instructions[*main..]
+        .iter()
+        .all(|x| matches!(x, Instructions::LABEL | Instructions::SKIP))
+    {
+      warnings.push(Warning::EmptyMain);
+    }
+    for (index, instruction) in self.instructions.iter().enumerate() {
+      if instruction.target() == Some("@MAIN") {
+        warnings.push(Warning::MainReentry(index));
+      }
+    }
+    warnings
+  }
+}
+
+#[cfg(test)]
+mod lint_test {
+  use crate::simulator::{
+    Simulator,
+    lint::{Severity, Warning},
+  };
+
+  #[test]
+  fn mnemonic_label_test() {
+    let lines: Vec<String> = vec![
+      String::from("@MAIN"),
+      String::from("@PRINT"),
+      String::from("LI $1 1"),
+    ];
+    let mut sim = Simulator::new();
+    sim.load(&lines).unwrap();
+    assert_eq!(
+      sim.lint(),
+      vec![Warning::MnemonicLabel(String::from("@PRINT"))]
+    );
+  }
+
+  #[test]
+  fn empty_main_test() {
+    let lines: Vec<String> = vec![
+      String::from("@LOOP"),
+      String::from("LI $1 1"),
+      String::from("@MAIN"),
+    ];
+    let mut sim = Simulator::new();
+    sim.load(&lines).unwrap();
+    assert_eq!(sim.lint(), vec![Warning::EmptyMain]);
+  }
+
+  #[test]
+  fn main_reentry_test() {
+    let lines: Vec<String> = vec![
+      String::from("@MAIN"),
+      String::from("LI $1 1"),
+      String::from("BEQ $1 $0 @MAIN"),
+    ];
+    let mut sim = Simulator::new();
+    sim.load(&lines).unwrap();
+    assert_eq!(sim.lint(), vec![Warning::MainReentry(2)]);
+    assert_eq!(sim.lint()[0].severity(), Severity::Info);
+  }
+
+  #[test]
+  fn severity_test() {
+    let lines: Vec<String> = vec![String::from("@ADD"), String::from("@MAIN")];
+    let mut sim = Simulator::new();
+    sim.load(&lines).unwrap();
+    let warnings = sim.lint();
+    let found: Vec<(Severity, &str)> = warnings.iter().map(|x| (x.severity(), x.code())).collect();
+    assert_eq!(
+      found,
+      vec![
+        (Severity::Warning, "mnemonic-label"),
+        (Severity::Error, "empty-main")
+      ]
+    );
+  }
+} // mod lint_test
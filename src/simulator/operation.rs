@@ -4,11 +4,20 @@
 //!
 //! operations related module
 
-use crate::simulator::{Error, Instructions, Simulator};
+use crate::simulator::{Error, INT_REGISTER_COUNT, Instructions, Simulator};
 
+/// Do the operation of the instruction at the program counter, failing if it is past the end.
 pub fn operate(sim: &mut Simulator) -> Result<(), Error> {
-  assert!(sim.program_counter < sim.instructions.len());
-  let instruction = sim.instructions[sim.program_counter].clone();
+  let instruction = sim
+    .instructions
+    .get(sim.program_counter)
+    .ok_or(Error::OutOfRange)?
+    .clone();
+  execute(sim, instruction)
+}
+
+/// Do the operation of the instruction, whether it belongs to the program or not.
+pub fn execute(sim: &mut Simulator, instruction: Instructions) -> Result<(), Error> {
   match instruction {
     Instructions::LI(a, b) => li_operation(sim, a, b),
     Instructions::MOVE(a, b) => move_operation(sim, a, b),
@@ -17,133 +26,304 @@ pub fn operate(sim: &mut Simulator) -> Result<(), Error> {
     Instructions::MUL(a, b, c) => mul_operation(sim, a, b, c),
     Instructions::DIV(a, b, c) => div_operation(sim, a, b, c),
     Instructions::REM(a, b, c) => rem_operation(sim, a, b, c),
-    Instructions::EXIT => exit_operation(),
+    Instructions::EXIT => exit_operation(sim),
     Instructions::SKIP => Ok(()),
     Instructions::JUMP(a) => jump_operation(sim, &a),
     Instructions::PRINT(a) => print_operation(sim, a),
+    Instructions::PRINTREGS => printregs_operation(sim),
+    Instructions::PRINTNN(a) => printnn_operation(sim, a),
+    Instructions::PRINTLN => println_operation(sim),
     Instructions::BEQ(a, b, c) => beq_operation(sim, a, b, &c),
     Instructions::BNE(a, b, c) => bne_operation(sim, a, b, &c),
     Instructions::BLT(a, b, c) => blt_operation(sim, a, b, &c),
     Instructions::BLE(a, b, c) => ble_operation(sim, a, b, &c),
     Instructions::BGT(a, b, c) => bgt_operation(sim, a, b, &c),
     Instructions::BGE(a, b, c) => bge_operation(sim, a, b, &c),
+    Instructions::BLTU(a, b, c) => bltu_operation(sim, a, b, &c),
+    Instructions::BLEU(a, b, c) => bleu_operation(sim, a, b, &c),
+    Instructions::BGTU(a, b, c) => bgtu_operation(sim, a, b, &c),
+    Instructions::BGEU(a, b, c) => bgeu_operation(sim, a, b, &c),
     Instructions::LABEL => Ok(()),
     Instructions::PUSH(a) => push_operation(sim, a),
     Instructions::POP(a) => pop_operation(sim, a),
+    Instructions::SKIPEQ(a, b) => skipeq_operation(sim, a, b),
+    Instructions::SKIPNE(a, b) => skipne_operation(sim, a, b),
+    Instructions::ABSDIFF(a, b, c) => absdiff_operation(sim, a, b, c),
+    Instructions::READALL(a, b) => readall_operation(sim, a, b),
+    Instructions::SP(a) => sp_operation(sim, a),
+    Instructions::VADD(a, b, c, d) => vadd_operation(sim, a, b, c, d),
+    Instructions::POWMOD(a, b, c, d) => powmod_operation(sim, a, b, c, d),
+    Instructions::BOV(a) => bov_operation(sim, &a),
+    Instructions::BSWAP(a, b) => bswap_operation(sim, a, b),
+    Instructions::RBIT(a, b) => rbit_operation(sim, a, b),
+    Instructions::SETEQ(a, b, c) => set_operation(sim, a, b, c, |x, y| x == y),
+    Instructions::SETNE(a, b, c) => set_operation(sim, a, b, c, |x, y| x != y),
+    Instructions::SETLT(a, b, c) => set_operation(sim, a, b, c, |x, y| x < y),
+    Instructions::SETLE(a, b, c) => set_operation(sim, a, b, c, |x, y| x <= y),
+    Instructions::SETGT(a, b, c) => set_operation(sim, a, b, c, |x, y| x > y),
+    Instructions::SETGE(a, b, c) => set_operation(sim, a, b, c, |x, y| x >= y),
+    Instructions::TAILCALL(a) => jump_operation(sim, &a),
+    Instructions::CALL(a) => call_operation(sim, &a),
+    Instructions::RET => ret_operation(sim),
+    Instructions::ADDI(a, b, c) => addi_operation(sim, a, b, c),
+    Instructions::SUBI(a, b, c) => subi_operation(sim, a, b, c),
+    Instructions::GCD(a, b, c) => gcd_operation(sim, a, b, c),
+    Instructions::AND(a, b, c) => and_operation(sim, a, b, c),
+    Instructions::OR(a, b, c) => or_operation(sim, a, b, c),
+    Instructions::XOR(a, b, c) => xor_operation(sim, a, b, c),
+    Instructions::NOT(a, b) => not_operation(sim, a, b),
+    Instructions::NEG(a, b) => neg_operation(sim, a, b),
+    Instructions::SHL(a, b, c) => shl_operation(sim, a, b, c),
+    Instructions::SHR(a, b, c) => shr_operation(sim, a, b, c),
+    Instructions::SLT(a, b, c) => set_operation(sim, a, b, c, |x, y| x < y),
+    Instructions::STEPS(a) => steps_operation(sim, a),
+    Instructions::NOP => Ok(()),
+    Instructions::PRINTC(a) => printc_operation(sim, a),
+    Instructions::SWAP(a, b) => swap_operation(sim, a, b),
+    Instructions::MIN(a, b, c) => min_operation(sim, a, b, c),
+    Instructions::MAX(a, b, c) => max_operation(sim, a, b, c),
+    Instructions::ABS(a, b) => abs_operation(sim, a, b),
+    Instructions::INC(a) => inc_operation(sim, a),
+    Instructions::DEC(a) => dec_operation(sim, a),
+    Instructions::CLO => {
+      sim.overflow = false;
+      Ok(())
+    }
   }
 }
 
-/// Do the LI instruction operation.
-fn li_operation(sim: &mut Simulator, a: usize, b: i32) -> Result<(), Error> {
-  if a >= sim.int_registers.len() {
-    Err(Error::OutOfRange)
+/// Fails with the first register that does not exist, and the instruction being executed.
+fn check_registers(sim: &Simulator, registers: &[usize]) -> Result<(), Error> {
+  match registers.iter().find(|&&x| x >= INT_REGISTER_COUNT) {
+    Some(&register) => Err(Error::RegisterOutOfRange {
+      register,
+      pc: sim.program_counter,
+    }),
+    None => Ok(()),
+  }
+}
+
+/// Like `check_registers`, for the `count` consecutive registers from `start`.
+fn check_register_span(sim: &Simulator, start: usize, count: usize) -> Result<(), Error> {
+  if start
+    .checked_add(count)
+    .is_none_or(|end| end > INT_REGISTER_COUNT)
+  {
+    Err(Error::RegisterOutOfRange {
+      register: start.max(INT_REGISTER_COUNT),
+      pc: sim.program_counter,
+    })
   } else {
-    sim.int_registers[a] = b;
     Ok(())
   }
 }
 
+/// Do the LI instruction operation.
+fn li_operation(sim: &mut Simulator, a: usize, b: i32) -> Result<(), Error> {
+  check_registers(sim, &[a])?;
+  sim.int_registers[a] = b;
+  Ok(())
+}
+
 /// Do the MOVE instruction operation.
 fn move_operation(sim: &mut Simulator, a: usize, b: usize) -> Result<(), Error> {
-  if a >= sim.int_registers.len() || b >= sim.int_registers.len() {
-    Err(Error::OutOfRange)
-  } else {
-    sim.int_registers[a] = sim.int_registers[b];
-    Ok(())
-  }
+  check_registers(sim, &[a, b])?;
+  sim.int_registers[a] = sim.int_registers[b];
+  Ok(())
 }
 
 /// Do the ADD instruction operation.
 fn add_operation(sim: &mut Simulator, a: usize, b: usize, c: usize) -> Result<(), Error> {
-  if a >= sim.int_registers.len() || b >= sim.int_registers.len() || c >= sim.int_registers.len() {
-    Err(Error::OutOfRange)
-  } else {
-    let result: i32 = sim.int_registers[b].wrapping_add(sim.int_registers[c]);
-    sim.int_registers[a] = result;
-    Ok(())
-  }
+  check_registers(sim, &[a, b, c])?;
+  let (result, overflow) = sim.int_registers[b].overflowing_add(sim.int_registers[c]);
+  sim.int_registers[a] = result;
+  sim.overflow = overflow;
+  Ok(())
 }
 
 /// Do the SUB instruction operation.
 fn sub_operation(sim: &mut Simulator, a: usize, b: usize, c: usize) -> Result<(), Error> {
-  if a >= sim.int_registers.len() || b >= sim.int_registers.len() || c >= sim.int_registers.len() {
-    Err(Error::OutOfRange)
-  } else {
-    let result: i32 = sim.int_registers[b].wrapping_sub(sim.int_registers[c]);
-    sim.int_registers[a] = result;
-    Ok(())
-  }
+  check_registers(sim, &[a, b, c])?;
+  let (result, overflow) = sim.int_registers[b].overflowing_sub(sim.int_registers[c]);
+  sim.int_registers[a] = result;
+  sim.overflow = overflow;
+  Ok(())
 }
 
 /// Do the MUL instruction operation.
 fn mul_operation(sim: &mut Simulator, a: usize, b: usize, c: usize) -> Result<(), Error> {
-  if a >= sim.int_registers.len() || b >= sim.int_registers.len() || c >= sim.int_registers.len() {
-    Err(Error::OutOfRange)
-  } else {
-    let result: i32 = sim.int_registers[b].wrapping_mul(sim.int_registers[c]);
-    sim.int_registers[a] = result;
-    Ok(())
-  }
+  check_registers(sim, &[a, b, c])?;
+  let (result, overflow) = sim.int_registers[b].overflowing_mul(sim.int_registers[c]);
+  sim.int_registers[a] = result;
+  sim.overflow = overflow;
+  Ok(())
 }
 
-/// Do the DIV instruction operation.
+/// Do the DIV instruction operation. i32::MIN / -1 wraps to i32::MIN, setting the overflow flag.
 fn div_operation(sim: &mut Simulator, a: usize, b: usize, c: usize) -> Result<(), Error> {
-  if a >= sim.int_registers.len() || b >= sim.int_registers.len() || c >= sim.int_registers.len() {
-    Err(Error::OutOfRange)
-  } else if sim.int_registers[c] == 0 {
+  check_registers(sim, &[a, b, c])?;
+  if sim.int_registers[c] == 0 {
     Err(Error::DivisionByZero)
   } else {
-    let result: i32 = sim.int_registers[b].wrapping_div(sim.int_registers[c]);
+    let (result, overflow) = sim.int_registers[b].overflowing_div(sim.int_registers[c]);
     sim.int_registers[a] = result;
+    sim.overflow = overflow;
     Ok(())
   }
 }
 
-/// Do the REM instruction operation.
+/// Do the REM instruction operation. i32::MIN % -1 gives 0, setting the overflow flag.
 fn rem_operation(sim: &mut Simulator, a: usize, b: usize, c: usize) -> Result<(), Error> {
-  if a >= sim.int_registers.len() || b >= sim.int_registers.len() || c >= sim.int_registers.len() {
-    Err(Error::OutOfRange)
-  } else if sim.int_registers[c] == 0 {
+  check_registers(sim, &[a, b, c])?;
+  if sim.int_registers[c] == 0 {
     Err(Error::DivisionByZero)
   } else {
-    let result: i32 = sim.int_registers[b].wrapping_rem(sim.int_registers[c]);
+    let (result, overflow) = sim.int_registers[b].overflowing_rem(sim.int_registers[c]);
     sim.int_registers[a] = result;
+    sim.overflow = overflow;
     Ok(())
   }
 }
 
+/// Do the ABSDIFF instruction operation.
+fn absdiff_operation(sim: &mut Simulator, a: usize, b: usize, c: usize) -> Result<(), Error> {
+  check_registers(sim, &[a, b, c])?;
+  let result: i32 = sim.int_registers[b]
+    .wrapping_sub(sim.int_registers[c])
+    .wrapping_abs();
+  sim.int_registers[a] = result;
+  Ok(())
+}
+
+/// Do the VADD instruction operation
+fn vadd_operation(
+  sim: &mut Simulator, a: usize, b: usize, c: usize, d: usize,
+) -> Result<(), Error> {
+  for start in [a, b, c] {
+    check_register_span(sim, start, d)?;
+  }
+  let source = sim.int_registers;
+  for n in 0..d {
+    sim.int_registers[a + n] = source[b + n].wrapping_add(source[c + n]);
+  }
+  Ok(())
+}
+
+/// Do the POWMOD instruction operation, by square and multiply.
+fn powmod_operation(
+  sim: &mut Simulator, a: usize, b: usize, c: usize, d: usize,
+) -> Result<(), Error> {
+  check_registers(sim, &[a, b, c, d])?;
+  if sim.int_registers[d] <= 0 || sim.int_registers[c] < 0 {
+    Err(Error::InvalidParameter)
+  } else {
+    let modulus = sim.int_registers[d] as u64;
+    let mut base = sim.int_registers[b].rem_euclid(sim.int_registers[d]) as u64;
+    let mut exp = sim.int_registers[c] as u64;
+    let mut result: u64 = 1 % modulus;
+    while exp > 0 {
+      if exp & 1 == 1 {
+        result = result * base % modulus;
+      }
+      base = base * base % modulus;
+      exp >>= 1;
+    }
+    sim.int_registers[a] = result as i32;
+    Ok(())
+  }
+}
+
+/// Do the READALL instruction operation
+fn readall_operation(sim: &mut Simulator, a: usize, b: usize) -> Result<(), Error> {
+  check_register_span(sim, a, b)?;
+  for reg in a..a + b {
+    sim.int_registers[reg] = sim.input.next_integer(sim.program_counter)?;
+  }
+  Ok(())
+}
+
 /// Do the PRINT instruction operation
 fn print_operation(sim: &mut Simulator, a: usize) -> Result<(), Error> {
-  if a >= sim.int_registers.len() {
-    Err(Error::OutOfRange)
+  check_registers(sim, &[a])?;
+  if sim.output.is_null() {
+    return Ok(());
+  }
+  sim.pending_separator = false;
+  let value = sim.value_format.format(sim.int_registers[a]);
+  let text = format!("PRINT: ${a}: {value}\n");
+  sim.output.write_value(a, sim.int_registers[a], &text)
+}
+
+/// Do the PRINTNN instruction operation, writing the value without newline.
+/// Values written by consecutive PRINTNN are separated by a space.
+fn printnn_operation(sim: &mut Simulator, a: usize) -> Result<(), Error> {
+  check_registers(sim, &[a])?;
+  if sim.output.is_null() {
+    return Ok(());
+  }
+  let value = sim.value_format.format(sim.int_registers[a]);
+  let text = if sim.pending_separator {
+    format!(" {value}")
   } else {
-    println!("PRINT: ${}: {}", a, sim.int_registers[a]);
-    Ok(())
+    value
+  };
+  sim.pending_separator = true;
+  sim.output.write(&text)
+}
+
+/// Do the PRINTLN instruction operation.
+fn println_operation(sim: &mut Simulator) -> Result<(), Error> {
+  sim.pending_separator = false;
+  sim.output.write("\n")
+}
+
+/// Do the PRINTREGS instruction operation
+fn printregs_operation(sim: &mut Simulator) -> Result<(), Error> {
+  if sim.output.is_null() {
+    return Ok(());
   }
+  sim.pending_separator = false;
+  let values: Vec<String> = sim
+    .int_registers
+    .iter()
+    .map(|x| sim.value_format.format(*x))
+    .collect();
+  let text = format!("{}\n", values.join(" "));
+  sim.output.write(&text)
 }
 
-/// Do the EXIT instruction operation
-fn exit_operation() -> Result<(), Error> {
-  println!("EXIT");
-  std::process::exit(0);
+/// Do the EXIT instruction operation.
+/// It always ends the whole program, whatever the subroutine call depth: the run returns `Ok`.
+fn exit_operation(sim: &mut Simulator) -> Result<(), Error> {
+  sim.exited = true;
+  sim.pending_separator = false;
+  sim.output.write("EXIT\n")
 }
 
 // Do the inconditional JUMP instruction operation
 fn jump_operation(sim: &mut Simulator, a: &str) -> Result<(), Error> {
-  let x = sim.labels.get(a);
-  if x.is_none() {
-    Err(Error::UnknownLabel)
+  match sim.labels.get(a) {
+    Some(x) => {
+      sim.program_counter = *x;
+      Ok(())
+    }
+    None => Err(Error::UnknownLabel),
+  }
+}
+
+/// Do the conditional BOV instruction operation
+fn bov_operation(sim: &mut Simulator, a: &str) -> Result<(), Error> {
+  if sim.overflow {
+    jump_operation(sim, a)
   } else {
-    sim.program_counter = *x.unwrap();
     Ok(())
   }
 }
 
 /// Do the conditional BEQ instruction operation
 fn beq_operation(sim: &mut Simulator, a: usize, b: usize, c: &str) -> Result<(), Error> {
-  if a >= sim.int_registers.len() || b >= sim.int_registers.len() {
-    Err(Error::OutOfRange)
-  } else if sim.int_registers[a] == sim.int_registers[b] {
+  check_registers(sim, &[a, b])?;
+  if sim.int_registers[a] == sim.int_registers[b] {
     jump_operation(sim, c)
   } else {
     Ok(())
@@ -152,9 +332,8 @@ fn beq_operation(sim: &mut Simulator, a: usize, b: usize, c: &str) -> Result<(),
 
 /// Do the conditional BNE instruction operation
 fn bne_operation(sim: &mut Simulator, a: usize, b: usize, c: &str) -> Result<(), Error> {
-  if a >= sim.int_registers.len() || b >= sim.int_registers.len() {
-    Err(Error::OutOfRange)
-  } else if sim.int_registers[a] != sim.int_registers[b] {
+  check_registers(sim, &[a, b])?;
+  if sim.int_registers[a] != sim.int_registers[b] {
     jump_operation(sim, c)
   } else {
     Ok(())
@@ -163,9 +342,8 @@ fn bne_operation(sim: &mut Simulator, a: usize, b: usize, c: &str) -> Result<(),
 
 /// Do the conditional BLT instruction operation
 fn blt_operation(sim: &mut Simulator, a: usize, b: usize, c: &str) -> Result<(), Error> {
-  if a >= sim.int_registers.len() || b >= sim.int_registers.len() {
-    Err(Error::OutOfRange)
-  } else if sim.int_registers[a] < sim.int_registers[b] {
+  check_registers(sim, &[a, b])?;
+  if sim.int_registers[a] < sim.int_registers[b] {
     jump_operation(sim, c)
   } else {
     Ok(())
@@ -174,9 +352,8 @@ fn blt_operation(sim: &mut Simulator, a: usize, b: usize, c: &str) -> Result<(),
 
 /// Do the conditional BLE instruction operation
 fn ble_operation(sim: &mut Simulator, a: usize, b: usize, c: &str) -> Result<(), Error> {
-  if a >= sim.int_registers.len() || b >= sim.int_registers.len() {
-    Err(Error::OutOfRange)
-  } else if sim.int_registers[a] <= sim.int_registers[b] {
+  check_registers(sim, &[a, b])?;
+  if sim.int_registers[a] <= sim.int_registers[b] {
     jump_operation(sim, c)
   } else {
     Ok(())
@@ -185,9 +362,8 @@ fn ble_operation(sim: &mut Simulator, a: usize, b: usize, c: &str) -> Result<(),
 
 /// Do the conditional BGT instruction operation
 fn bgt_operation(sim: &mut Simulator, a: usize, b: usize, c: &str) -> Result<(), Error> {
-  if a >= sim.int_registers.len() || b >= sim.int_registers.len() {
-    Err(Error::OutOfRange)
-  } else if sim.int_registers[a] > sim.int_registers[b] {
+  check_registers(sim, &[a, b])?;
+  if sim.int_registers[a] > sim.int_registers[b] {
     jump_operation(sim, c)
   } else {
     Ok(())
@@ -196,34 +372,880 @@ fn bgt_operation(sim: &mut Simulator, a: usize, b: usize, c: &str) -> Result<(),
 
 /// Do the conditional BGE instruction operation
 fn bge_operation(sim: &mut Simulator, a: usize, b: usize, c: &str) -> Result<(), Error> {
-  if a >= sim.int_registers.len() || b >= sim.int_registers.len() {
-    Err(Error::OutOfRange)
-  } else if sim.int_registers[a] >= sim.int_registers[b] {
+  check_registers(sim, &[a, b])?;
+  if sim.int_registers[a] >= sim.int_registers[b] {
     jump_operation(sim, c)
   } else {
     Ok(())
   }
 }
 
-/// Do the stack operation PUSH
-fn push_operation(sim: &mut Simulator, a: usize) -> Result<(), Error> {
-  if a >= sim.int_registers.len() {
-    Err(Error::OutOfRange)
+/// Do the conditional BLTU instruction operation
+fn bltu_operation(sim: &mut Simulator, a: usize, b: usize, c: &str) -> Result<(), Error> {
+  check_registers(sim, &[a, b])?;
+  if (sim.int_registers[a] as u32) < (sim.int_registers[b] as u32) {
+    jump_operation(sim, c)
+  } else {
+    Ok(())
+  }
+}
+
+/// Do the conditional BLEU instruction operation
+fn bleu_operation(sim: &mut Simulator, a: usize, b: usize, c: &str) -> Result<(), Error> {
+  check_registers(sim, &[a, b])?;
+  if (sim.int_registers[a] as u32) <= (sim.int_registers[b] as u32) {
+    jump_operation(sim, c)
+  } else {
+    Ok(())
   }
-  else {
-    sim.stack.push(sim.int_registers[a]);
+}
+
+/// Do the conditional BGTU instruction operation
+fn bgtu_operation(sim: &mut Simulator, a: usize, b: usize, c: &str) -> Result<(), Error> {
+  check_registers(sim, &[a, b])?;
+  if (sim.int_registers[a] as u32) > (sim.int_registers[b] as u32) {
+    jump_operation(sim, c)
+  } else {
     Ok(())
   }
 }
 
+/// Do the conditional BGEU instruction operation
+fn bgeu_operation(sim: &mut Simulator, a: usize, b: usize, c: &str) -> Result<(), Error> {
+  check_registers(sim, &[a, b])?;
+  if (sim.int_registers[a] as u32) >= (sim.int_registers[b] as u32) {
+    jump_operation(sim, c)
+  } else {
+    Ok(())
+  }
+}
+
+/// Do the stack operation PUSH
+fn push_operation(sim: &mut Simulator, a: usize) -> Result<(), Error> {
+  check_registers(sim, &[a])?;
+  sim.stack.push(sim.int_registers[a]);
+  sim.max_stack_depth = sim.max_stack_depth.max(sim.stack.len());
+  Ok(())
+}
+
 /// Do the stack operation POP
 fn pop_operation(sim: &mut Simulator, a: usize) -> Result<(), Error> {
-  if a >= sim.int_registers.len() {
+  check_registers(sim, &[a])?;
+  let value = sim.stack.pop().ok_or(Error::StackUnderflow)?;
+  sim.int_registers[a] = value;
+  Ok(())
+}
+
+/// Do the stack operation SP
+fn sp_operation(sim: &mut Simulator, a: usize) -> Result<(), Error> {
+  check_registers(sim, &[a])?;
+  sim.int_registers[a] = i32::try_from(sim.stack.len()).unwrap_or(i32::MAX);
+  Ok(())
+}
+
+/// Skip the next instruction, failing if there is no next instruction.
+fn skip_next(sim: &mut Simulator) -> Result<(), Error> {
+  if sim.program_counter + 1 >= sim.instructions.len() {
     Err(Error::OutOfRange)
+  } else {
+    sim.program_counter += 1;
+    Ok(())
   }
-  else {
-    let value = sim.stack.pop().ok_or(Error::EmptyStack)?;
-    sim.int_registers[a] = value;
+}
+
+/// Do the conditional SKIPEQ instruction operation
+fn skipeq_operation(sim: &mut Simulator, a: usize, b: usize) -> Result<(), Error> {
+  check_registers(sim, &[a, b])?;
+  if sim.int_registers[a] == sim.int_registers[b] {
+    skip_next(sim)
+  } else {
+    Ok(())
+  }
+}
+
+/// Do the conditional SKIPNE instruction operation
+fn skipne_operation(sim: &mut Simulator, a: usize, b: usize) -> Result<(), Error> {
+  check_registers(sim, &[a, b])?;
+  if sim.int_registers[a] != sim.int_registers[b] {
+    skip_next(sim)
+  } else {
     Ok(())
   }
-}
\ No newline at end of file
+}
+
+/// Do the BSWAP instruction operation, reversing the bytes of the u32 value.
+fn bswap_operation(sim: &mut Simulator, a: usize, b: usize) -> Result<(), Error> {
+  check_registers(sim, &[a, b])?;
+  sim.int_registers[a] = (sim.int_registers[b] as u32).swap_bytes() as i32;
+  Ok(())
+}
+
+/// Do the RBIT instruction operation, reversing the bits of the u32 value.
+fn rbit_operation(sim: &mut Simulator, a: usize, b: usize) -> Result<(), Error> {
+  check_registers(sim, &[a, b])?;
+  sim.int_registers[a] = (sim.int_registers[b] as u32).reverse_bits() as i32;
+  Ok(())
+}
+
+/// Do the SETxx and SLT instruction operations, setting a to 1 if the comparison of b and c holds, or to 0.
+fn set_operation(
+  sim: &mut Simulator, a: usize, b: usize, c: usize, compare: fn(i32, i32) -> bool,
+) -> Result<(), Error> {
+  check_registers(sim, &[a, b, c])?;
+  sim.int_registers[a] = compare(sim.int_registers[b], sim.int_registers[c]) as i32;
+  Ok(())
+}
+
+/// Do the CALL instruction operation, saving the current position in the call stack.
+fn call_operation(sim: &mut Simulator, a: &str) -> Result<(), Error> {
+  let return_address = sim.program_counter;
+  jump_operation(sim, a)?;
+  sim.call_stack.push(return_address);
+  sim.max_call_depth = sim.max_call_depth.max(sim.call_stack.len());
+  Ok(())
+}
+
+/// Do the RET instruction operation, going back to the last CALL (the next instruction runs after it).
+fn ret_operation(sim: &mut Simulator) -> Result<(), Error> {
+  sim.program_counter = sim.call_stack.pop().ok_or(Error::StackUnderflow)?;
+  Ok(())
+}
+
+/// Do the ADDI instruction operation.
+fn addi_operation(sim: &mut Simulator, a: usize, b: usize, c: i32) -> Result<(), Error> {
+  check_registers(sim, &[a, b])?;
+  let (result, overflow) = sim.int_registers[b].overflowing_add(c);
+  sim.int_registers[a] = result;
+  sim.overflow = overflow;
+  Ok(())
+}
+
+/// Do the SUBI instruction operation.
+fn subi_operation(sim: &mut Simulator, a: usize, b: usize, c: i32) -> Result<(), Error> {
+  check_registers(sim, &[a, b])?;
+  let (result, overflow) = sim.int_registers[b].overflowing_sub(c);
+  sim.int_registers[a] = result;
+  sim.overflow = overflow;
+  Ok(())
+}
+
+/// Do the GCD instruction operation, by the Euclidean algorithm over the absolute values.
+/// gcd(0, 0) is 0, and gcd(i32::MIN, 0) wraps to i32::MIN like ABSDIFF.
+fn gcd_operation(sim: &mut Simulator, a: usize, b: usize, c: usize) -> Result<(), Error> {
+  check_registers(sim, &[a, b, c])?;
+  let mut x = sim.int_registers[b].unsigned_abs();
+  let mut y = sim.int_registers[c].unsigned_abs();
+  while y != 0 {
+    (x, y) = (y, x % y);
+  }
+  sim.int_registers[a] = x as i32;
+  Ok(())
+}
+
+/// Do the AND instruction operation.
+fn and_operation(sim: &mut Simulator, a: usize, b: usize, c: usize) -> Result<(), Error> {
+  check_registers(sim, &[a, b, c])?;
+  sim.int_registers[a] = sim.int_registers[b] & sim.int_registers[c];
+  Ok(())
+}
+
+/// Do the OR instruction operation.
+fn or_operation(sim: &mut Simulator, a: usize, b: usize, c: usize) -> Result<(), Error> {
+  check_registers(sim, &[a, b, c])?;
+  sim.int_registers[a] = sim.int_registers[b] | sim.int_registers[c];
+  Ok(())
+}
+
+/// Do the XOR instruction operation.
+fn xor_operation(sim: &mut Simulator, a: usize, b: usize, c: usize) -> Result<(), Error> {
+  check_registers(sim, &[a, b, c])?;
+  sim.int_registers[a] = sim.int_registers[b] ^ sim.int_registers[c];
+  Ok(())
+}
+
+/// Do the NOT instruction operation.
+fn not_operation(sim: &mut Simulator, a: usize, b: usize) -> Result<(), Error> {
+  check_registers(sim, &[a, b])?;
+  sim.int_registers[a] = !sim.int_registers[b];
+  Ok(())
+}
+
+/// Do the NEG instruction operation. i32::MIN wraps to itself, setting the overflow flag.
+fn neg_operation(sim: &mut Simulator, a: usize, b: usize) -> Result<(), Error> {
+  check_registers(sim, &[a, b])?;
+  let (result, overflow) = sim.int_registers[b].overflowing_neg();
+  sim.int_registers[a] = result;
+  sim.overflow = overflow;
+  Ok(())
+}
+
+/// Do the SHL instruction operation. Only the low 5 bits of the amount are used.
+fn shl_operation(sim: &mut Simulator, a: usize, b: usize, c: usize) -> Result<(), Error> {
+  check_registers(sim, &[a, b, c])?;
+  sim.int_registers[a] = sim.int_registers[b] << (sim.int_registers[c] & 31);
+  Ok(())
+}
+
+/// Do the SHR instruction operation, keeping the sign. Only the low 5 bits of the amount are used.
+fn shr_operation(sim: &mut Simulator, a: usize, b: usize, c: usize) -> Result<(), Error> {
+  check_registers(sim, &[a, b, c])?;
+  sim.int_registers[a] = sim.int_registers[b] >> (sim.int_registers[c] & 31);
+  Ok(())
+}
+
+/// Do the STEPS instruction operation. The count includes the STEPS instruction itself.
+fn steps_operation(sim: &mut Simulator, a: usize) -> Result<(), Error> {
+  check_registers(sim, &[a])?;
+  sim.int_registers[a] = i32::try_from(sim.steps).unwrap_or(i32::MAX);
+  Ok(())
+}
+
+/// Do the PRINTC instruction operation, writing the low byte of the value as an ASCII character.
+/// Bytes over 127 are not ASCII, so they are written as the replacement character.
+fn printc_operation(sim: &mut Simulator, a: usize) -> Result<(), Error> {
+  check_registers(sim, &[a])?;
+  sim.pending_separator = false;
+  let byte = sim.int_registers[a] as u8;
+  let character = if byte.is_ascii() {
+    byte as char
+  } else {
+    char::REPLACEMENT_CHARACTER
+  };
+  sim.output.write(character.encode_utf8(&mut [0; 4]))
+}
+
+/// Do the SWAP instruction operation.
+fn swap_operation(sim: &mut Simulator, a: usize, b: usize) -> Result<(), Error> {
+  check_registers(sim, &[a, b])?;
+  sim.int_registers.swap(a, b);
+  Ok(())
+}
+
+/// Do the MIN instruction operation.
+fn min_operation(sim: &mut Simulator, a: usize, b: usize, c: usize) -> Result<(), Error> {
+  check_registers(sim, &[a, b, c])?;
+  sim.int_registers[a] = sim.int_registers[b].min(sim.int_registers[c]);
+  Ok(())
+}
+
+/// Do the MAX instruction operation.
+fn max_operation(sim: &mut Simulator, a: usize, b: usize, c: usize) -> Result<(), Error> {
+  check_registers(sim, &[a, b, c])?;
+  sim.int_registers[a] = sim.int_registers[b].max(sim.int_registers[c]);
+  Ok(())
+}
+
+/// Do the ABS instruction operation. i32::MIN wraps to itself, setting the overflow flag.
+fn abs_operation(sim: &mut Simulator, a: usize, b: usize) -> Result<(), Error> {
+  check_registers(sim, &[a, b])?;
+  let (result, overflow) = sim.int_registers[b].overflowing_abs();
+  sim.int_registers[a] = result;
+  sim.overflow = overflow;
+  Ok(())
+}
+
+/// Do the INC instruction operation. i32::MAX wraps to i32::MIN, setting the overflow flag.
+fn inc_operation(sim: &mut Simulator, a: usize) -> Result<(), Error> {
+  check_registers(sim, &[a])?;
+  let (result, overflow) = sim.int_registers[a].overflowing_add(1);
+  sim.int_registers[a] = result;
+  sim.overflow = overflow;
+  Ok(())
+}
+
+/// Do the DEC instruction operation. i32::MIN wraps to i32::MAX, setting the overflow flag.
+fn dec_operation(sim: &mut Simulator, a: usize) -> Result<(), Error> {
+  check_registers(sim, &[a])?;
+  let (result, overflow) = sim.int_registers[a].overflowing_sub(1);
+  sim.int_registers[a] = result;
+  sim.overflow = overflow;
+  Ok(())
+}
+
+#[cfg(test)]
+mod operation_test {
+  use crate::simulator::{Error, Instructions, Simulator, operation::operate, output::ValueFormat};
+
+  fn simulator(instructions: &[Instructions]) -> Simulator {
+    let mut sim = Simulator::new();
+    sim.instructions = instructions.to_vec();
+    sim
+  }
+
+  #[test]
+  fn register_out_of_range_test() {
+    let mut sim = simulator(&[Instructions::SKIP, Instructions::LI(40, 1)]);
+    sim.program_counter = 1;
+    let err = operate(&mut sim).unwrap_err();
+    assert_eq!(
+      err,
+      Error::RegisterOutOfRange {
+        register: 40,
+        pc: 1
+      }
+    );
+    assert_eq!(
+      err.to_string(),
+      "register $40 out of range at instruction 1"
+    );
+  }
+
+  #[test]
+  fn overflow_flag_test() {
+    let mut sim = simulator(&[
+      Instructions::ADD(3, 1, 2),
+      Instructions::BOV(String::from("@OVERFLOW")),
+      Instructions::CLO,
+    ]);
+    sim.labels.insert(String::from("@OVERFLOW"), 7);
+    sim.int_registers[1] = i32::MAX;
+    sim.int_registers[2] = 1;
+    operate(&mut sim).unwrap();
+    assert_eq!(sim.int_registers[3], i32::MIN);
+    assert!(sim.overflow);
+    sim.program_counter = 1;
+    operate(&mut sim).unwrap();
+    assert_eq!(sim.program_counter, 7);
+
+    sim.program_counter = 2;
+    operate(&mut sim).unwrap();
+    assert!(!sim.overflow);
+
+    sim.overflow = true;
+    sim.int_registers[1] = 1;
+    sim.program_counter = 0;
+    operate(&mut sim).unwrap();
+    assert!(!sim.overflow);
+    sim.program_counter = 1;
+    operate(&mut sim).unwrap();
+    assert_eq!(sim.program_counter, 1);
+  }
+
+  #[test]
+  fn value_format_test() {
+    let mut sim = simulator(&[Instructions::PRINT(1)]).with_captured_output();
+    sim.int_registers[1] = 255;
+    operate(&mut sim).unwrap();
+    assert_eq!(sim.take_output(), "PRINT: $1: 255\n");
+
+    let mut sim = simulator(&[Instructions::PRINT(1)])
+      .with_captured_output()
+      .with_value_format(ValueFormat::Hex);
+    sim.int_registers[1] = 255;
+    operate(&mut sim).unwrap();
+    sim.int_registers[1] = -1;
+    operate(&mut sim).unwrap();
+    assert_eq!(
+      sim.take_output(),
+      "PRINT: $1: 0xff\nPRINT: $1: 0xffffffff\n"
+    );
+  }
+
+  #[test]
+  fn printnn_test() {
+    let mut sim = simulator(&[
+      Instructions::PRINTNN(1),
+      Instructions::PRINTNN(2),
+      Instructions::PRINTLN,
+    ])
+    .with_captured_output();
+    sim.int_registers[1] = 3;
+    sim.int_registers[2] = -4;
+    for n in 0..3 {
+      sim.program_counter = n;
+      operate(&mut sim).unwrap();
+    }
+    assert_eq!(sim.take_output(), "3 -4\n");
+    for n in 0..3 {
+      sim.program_counter = n;
+      operate(&mut sim).unwrap();
+    }
+    assert_eq!(sim.take_output(), "3 -4\n");
+  }
+
+  #[test]
+  fn printregs_test() {
+    let mut sim = simulator(&[Instructions::PRINTREGS]).with_captured_output();
+    sim.int_registers[1] = 7;
+    sim.int_registers[31] = -2;
+    operate(&mut sim).unwrap();
+    let expected = format!("0 7{} -2\n", " 0".repeat(29));
+    assert_eq!(sim.take_output(), expected);
+  }
+
+  #[test]
+  fn absdiff_test() {
+    let mut sim = simulator(&[Instructions::ABSDIFF(3, 1, 2)]);
+    sim.int_registers[1] = 2;
+    sim.int_registers[2] = 9;
+    operate(&mut sim).unwrap();
+    assert_eq!(sim.int_registers[3], 7);
+
+    sim.int_registers[1] = 9;
+    sim.int_registers[2] = 2;
+    operate(&mut sim).unwrap();
+    assert_eq!(sim.int_registers[3], 7);
+
+    // i32::MIN - 0 has no positive counterpart, so it wraps to itself.
+    sim.int_registers[1] = i32::MIN;
+    sim.int_registers[2] = 0;
+    operate(&mut sim).unwrap();
+    assert_eq!(sim.int_registers[3], i32::MIN);
+  }
+
+  #[test]
+  fn vadd_test() {
+    let mut sim = simulator(&[Instructions::VADD(10, 1, 4, 3)]);
+    sim.int_registers[1..7].copy_from_slice(&[1, 2, 3, 10, 20, 30]);
+    operate(&mut sim).unwrap();
+    assert_eq!(sim.int_registers[10..13], [11, 22, 33]);
+
+    let mut sim = simulator(&[Instructions::VADD(30, 1, 4, 3)]);
+    assert_eq!(
+      operate(&mut sim),
+      Err(Error::RegisterOutOfRange {
+        register: 32,
+        pc: 0
+      })
+    );
+  }
+
+  #[test]
+  fn powmod_test() {
+    let cases = [
+      (3, 4, 5, 1),
+      (2, 10, 1000, 24),
+      (-2, 3, 7, 6),
+      (7, 0, 13, 1),
+      (5, 3, 1, 0),
+      (i32::MAX, i32::MAX, i32::MAX - 1, 1),
+    ];
+    for (base, exp, modulus, expected) in cases {
+      let mut sim = simulator(&[Instructions::POWMOD(1, 2, 3, 4)]);
+      sim.int_registers[2..5].copy_from_slice(&[base, exp, modulus]);
+      operate(&mut sim).unwrap();
+      assert_eq!(sim.int_registers[1], expected, "{base}^{exp} mod {modulus}");
+    }
+
+    let mut sim = simulator(&[Instructions::POWMOD(1, 2, 3, 4)]);
+    sim.int_registers[2..5].copy_from_slice(&[3, 4, 0]);
+    assert_eq!(operate(&mut sim), Err(Error::InvalidParameter));
+    sim.int_registers[2..5].copy_from_slice(&[3, -1, 5]);
+    assert_eq!(operate(&mut sim), Err(Error::InvalidParameter));
+  }
+
+  #[test]
+  fn readall_test() {
+    let mut sim = simulator(&[Instructions::READALL(4, 3)]).with_input("7 -2\n 13");
+    operate(&mut sim).unwrap();
+    assert_eq!(sim.int_registers[4..7], [7, -2, 13]);
+    assert_eq!(operate(&mut sim), Err(Error::EndOfInput));
+
+    let mut sim = simulator(&[Instructions::READALL(30, 3)]).with_input("1 2 3");
+    assert_eq!(
+      operate(&mut sim),
+      Err(Error::RegisterOutOfRange {
+        register: 32,
+        pc: 0
+      })
+    );
+
+    let mut sim = simulator(&[Instructions::READALL(1, 1)]).with_input("x");
+    assert_eq!(operate(&mut sim), Err(Error::InvalidInput));
+  }
+
+  #[test]
+  fn unsigned_branch_test() {
+    let mut sim = simulator(&[
+      Instructions::BGTU(1, 2, String::from("@END")),
+      Instructions::BGT(1, 2, String::from("@END")),
+    ]);
+    sim.labels.insert(String::from("@END"), 5);
+    sim.int_registers[1] = -1;
+    sim.int_registers[2] = 1;
+    operate(&mut sim).unwrap();
+    assert_eq!(sim.program_counter, 5);
+    sim.program_counter = 1;
+    operate(&mut sim).unwrap();
+    assert_eq!(sim.program_counter, 1);
+  }
+
+  #[test]
+  fn sp_test() {
+    let mut sim = simulator(&[
+      Instructions::PUSH(1),
+      Instructions::PUSH(1),
+      Instructions::PUSH(1),
+      Instructions::POP(2),
+      Instructions::SP(3),
+    ]);
+    for n in 0..5 {
+      sim.program_counter = n;
+      operate(&mut sim).unwrap();
+    }
+    assert_eq!(sim.int_registers[3], 2);
+  }
+
+  #[test]
+  fn skipeq_taken_test() {
+    let mut sim = simulator(&[Instructions::SKIPEQ(1, 2), Instructions::LI(3, 1)]);
+    operate(&mut sim).unwrap();
+    assert_eq!(sim.program_counter, 1);
+  }
+
+  #[test]
+  fn skipeq_not_taken_test() {
+    let mut sim = simulator(&[Instructions::SKIPEQ(1, 2), Instructions::LI(3, 1)]);
+    sim.int_registers[1] = 1;
+    operate(&mut sim).unwrap();
+    assert_eq!(sim.program_counter, 0);
+  }
+
+  #[test]
+  fn skipne_taken_test() {
+    let mut sim = simulator(&[Instructions::SKIPNE(1, 2), Instructions::LI(3, 1)]);
+    sim.int_registers[1] = 1;
+    operate(&mut sim).unwrap();
+    assert_eq!(sim.program_counter, 1);
+  }
+
+  #[test]
+  fn skipne_not_taken_test() {
+    let mut sim = simulator(&[Instructions::SKIPNE(1, 2), Instructions::LI(3, 1)]);
+    operate(&mut sim).unwrap();
+    assert_eq!(sim.program_counter, 0);
+  }
+
+  #[test]
+  fn branch_past_end_test() {
+    let mut sim = simulator(&[Instructions::BEQ(1, 2, String::from("@END"))]);
+    sim.labels.insert(String::from("@END"), 1);
+    operate(&mut sim).unwrap();
+    assert_eq!(sim.program_counter, 1);
+    assert_eq!(operate(&mut sim), Err(Error::OutOfRange));
+  }
+
+  #[test]
+  fn skip_past_end_test() {
+    let mut sim = simulator(&[Instructions::SKIPEQ(1, 2)]);
+    assert_eq!(operate(&mut sim), Err(Error::OutOfRange));
+    sim.int_registers[1] = 1;
+    assert_eq!(operate(&mut sim), Ok(()));
+    assert_eq!(sim.program_counter, 0);
+
+    let mut sim = simulator(&[Instructions::SKIP, Instructions::SKIPNE(1, 2)]);
+    sim.program_counter = 1;
+    assert_eq!(operate(&mut sim), Ok(()));
+    sim.int_registers[1] = 1;
+    assert_eq!(operate(&mut sim), Err(Error::OutOfRange));
+    assert_eq!(sim.program_counter, 1);
+  }
+
+  #[test]
+  fn bswap_rbit_test() {
+    let mut sim = simulator(&[Instructions::BSWAP(2, 1), Instructions::RBIT(3, 1)]);
+    sim.int_registers[1] = 0x01020304;
+    operate(&mut sim).unwrap();
+    assert_eq!(sim.int_registers[2], 0x04030201);
+    sim.program_counter = 1;
+    operate(&mut sim).unwrap();
+    assert_eq!(sim.int_registers[3], 0x20C04080);
+
+    sim.int_registers[1] = 1;
+    operate(&mut sim).unwrap();
+    assert_eq!(sim.int_registers[3], i32::MIN);
+  }
+
+  #[test]
+  fn set_test() {
+    // (instruction, result for 1 and 2, result for 2 and 2, result for 3 and 2)
+    let cases = [
+      (Instructions::SETEQ(3, 1, 2), [0, 1, 0]),
+      (Instructions::SETNE(3, 1, 2), [1, 0, 1]),
+      (Instructions::SETLT(3, 1, 2), [1, 0, 0]),
+      (Instructions::SETLE(3, 1, 2), [1, 1, 0]),
+      (Instructions::SETGT(3, 1, 2), [0, 0, 1]),
+      (Instructions::SETGE(3, 1, 2), [0, 1, 1]),
+    ];
+    for (instruction, expected) in cases {
+      let mut sim = simulator(&[instruction]);
+      sim.int_registers[2] = 2;
+      for (value, result) in [1, 2, 3].into_iter().zip(expected) {
+        sim.int_registers[1] = value;
+        sim.int_registers[3] = -1;
+        operate(&mut sim).unwrap();
+        assert_eq!(sim.int_registers[3], result);
+      }
+    }
+  }
+
+  #[test]
+  fn tailcall_test() {
+    let mut sim = simulator(&[
+      Instructions::LABEL,
+      Instructions::TAILCALL(String::from("@LOOP")),
+    ]);
+    sim.labels.insert(String::from("@LOOP"), 0);
+    sim.program_counter = 1;
+    operate(&mut sim).unwrap();
+    assert_eq!(sim.program_counter, 0);
+    assert!(sim.stack.is_empty());
+  }
+
+  #[test]
+  fn push_pop_test() {
+    let mut sim = simulator(&[
+      Instructions::PUSH(1),
+      Instructions::PUSH(2),
+      Instructions::POP(3),
+      Instructions::POP(4),
+      Instructions::POP(5),
+    ]);
+    sim.int_registers[1] = 10;
+    sim.int_registers[2] = 20;
+    for n in 0..4 {
+      sim.program_counter = n;
+      operate(&mut sim).unwrap();
+    }
+    assert_eq!(sim.int_registers[3], 20);
+    assert_eq!(sim.int_registers[4], 10);
+    sim.program_counter = 4;
+    assert_eq!(operate(&mut sim), Err(Error::StackUnderflow));
+  }
+
+  #[test]
+  fn call_ret_test() {
+    let mut sim = simulator(&[
+      Instructions::CALL(String::from("@ROUTINE")),
+      Instructions::LABEL,
+      Instructions::RET,
+    ]);
+    sim.labels.insert(String::from("@ROUTINE"), 1);
+    operate(&mut sim).unwrap();
+    assert_eq!(sim.program_counter, 1);
+    assert_eq!(sim.call_stack.len(), 1);
+    sim.program_counter = 2;
+    operate(&mut sim).unwrap();
+    assert_eq!(sim.program_counter, 0);
+    assert!(sim.call_stack.is_empty());
+    sim.program_counter = 2;
+    assert_eq!(operate(&mut sim), Err(Error::StackUnderflow));
+  }
+
+  #[test]
+  fn immediate_arithmetic_test() {
+    let mut sim = simulator(&[Instructions::ADDI(2, 1, 5), Instructions::SUBI(3, 1, -5)]);
+    sim.int_registers[1] = 10;
+    operate(&mut sim).unwrap();
+    assert_eq!(sim.int_registers[2], 15);
+    sim.program_counter = 1;
+    operate(&mut sim).unwrap();
+    assert_eq!(sim.int_registers[3], 15);
+
+    let mut sim = simulator(&[Instructions::SUBI(2, 1, 1)]);
+    sim.int_registers[1] = i32::MIN;
+    operate(&mut sim).unwrap();
+    assert_eq!(sim.int_registers[2], i32::MAX);
+    assert!(sim.overflow);
+  }
+
+  #[test]
+  fn gcd_test() {
+    let cases = [
+      (12, 18, 6),
+      (-12, 18, 6),
+      (7, 0, 7),
+      (0, -7, 7),
+      (0, 0, 0),
+      (17, 5, 1),
+    ];
+    for (x, y, expected) in cases {
+      let mut sim = simulator(&[Instructions::GCD(3, 1, 2)]);
+      sim.int_registers[1] = x;
+      sim.int_registers[2] = y;
+      operate(&mut sim).unwrap();
+      assert_eq!(sim.int_registers[3], expected);
+    }
+  }
+
+  #[test]
+  fn bitwise_test() {
+    let mut sim = simulator(&[
+      Instructions::AND(3, 1, 2),
+      Instructions::OR(4, 1, 2),
+      Instructions::XOR(5, 1, 2),
+    ]);
+    sim.int_registers[1] = 0b1100;
+    sim.int_registers[2] = 0b1010;
+    for n in 0..3 {
+      sim.program_counter = n;
+      operate(&mut sim).unwrap();
+    }
+    assert_eq!(sim.int_registers[3], 0b1000);
+    assert_eq!(sim.int_registers[4], 0b1110);
+    assert_eq!(sim.int_registers[5], 0b0110);
+  }
+
+  #[test]
+  fn not_neg_test() {
+    let mut sim = simulator(&[
+      Instructions::LI(1, 5),
+      Instructions::NEG(2, 1),
+      Instructions::PRINT(2),
+      Instructions::NOT(3, 1),
+      Instructions::NEG(4, 5),
+    ])
+    .with_captured_output();
+    sim.int_registers[5] = i32::MIN;
+    for n in 0..5 {
+      sim.program_counter = n;
+      operate(&mut sim).unwrap();
+    }
+    assert_eq!(sim.take_output(), "PRINT: $2: -5\n");
+    assert_eq!(sim.int_registers[3], -6);
+    assert_eq!(sim.int_registers[4], i32::MIN);
+  }
+
+  #[test]
+  fn shift_test() {
+    let cases = [
+      (Instructions::SHL(3, 1, 2), 1, 4, 16),
+      (Instructions::SHL(3, 1, 2), 1, 36, 16),
+      (Instructions::SHL(3, 1, 2), 1, 31, i32::MIN),
+      (Instructions::SHR(3, 1, 2), 16, 4, 1),
+      (Instructions::SHR(3, 1, 2), -16, 2, -4),
+      (Instructions::SHR(3, 1, 2), 16, -1, 0),
+    ];
+    for (instruction, value, amount, expected) in cases {
+      let mut sim = simulator(&[instruction]);
+      sim.int_registers[1] = value;
+      sim.int_registers[2] = amount;
+      operate(&mut sim).unwrap();
+      assert_eq!(sim.int_registers[3], expected);
+    }
+  }
+
+  #[test]
+  fn slt_test() {
+    let mut sim = simulator(&[Instructions::SLT(3, 1, 2)]);
+    sim.int_registers[1] = 3;
+    sim.int_registers[2] = 5;
+    operate(&mut sim).unwrap();
+    assert_eq!(sim.int_registers[3], 1);
+    sim.int_registers[1] = 5;
+    sim.int_registers[2] = 3;
+    operate(&mut sim).unwrap();
+    assert_eq!(sim.int_registers[3], 0);
+  }
+
+  #[test]
+  fn printc_test() {
+    let mut sim = simulator(&[Instructions::PRINTC(1)]).with_captured_output();
+    for value in [65, 0x142, 10, 200] {
+      sim.int_registers[1] = value;
+      operate(&mut sim).unwrap();
+    }
+    assert_eq!(sim.take_output(), "AB\n\u{fffd}");
+  }
+
+  #[test]
+  fn swap_test() {
+    let mut sim = simulator(&[Instructions::SWAP(1, 2), Instructions::SWAP(1, 40)]);
+    sim.int_registers[1] = 3;
+    sim.int_registers[2] = 5;
+    operate(&mut sim).unwrap();
+    assert_eq!(sim.int_registers[1], 5);
+    assert_eq!(sim.int_registers[2], 3);
+    sim.program_counter = 1;
+    assert_eq!(
+      operate(&mut sim),
+      Err(Error::RegisterOutOfRange {
+        register: 40,
+        pc: 1
+      })
+    );
+  }
+
+  #[test]
+  fn min_max_test() {
+    let mut sim = simulator(&[Instructions::MIN(3, 1, 2), Instructions::MAX(4, 1, 2)]);
+    sim.int_registers[1] = -4;
+    sim.int_registers[2] = 7;
+    operate(&mut sim).unwrap();
+    sim.program_counter = 1;
+    operate(&mut sim).unwrap();
+    assert_eq!(sim.int_registers[3], -4);
+    assert_eq!(sim.int_registers[4], 7);
+  }
+
+  #[test]
+  fn abs_test() {
+    let mut sim = simulator(&[Instructions::ABS(2, 1)]);
+    for (value, expected) in [(5, 5), (-5, 5), (i32::MIN, i32::MIN)] {
+      sim.int_registers[1] = value;
+      operate(&mut sim).unwrap();
+      assert_eq!(sim.int_registers[2], expected);
+    }
+  }
+
+  #[test]
+  fn inc_dec_test() {
+    let mut sim = simulator(&[
+      Instructions::INC(1),
+      Instructions::BOV(String::from("@OVERFLOW")),
+      Instructions::DEC(1),
+    ]);
+    sim.labels.insert(String::from("@OVERFLOW"), 7);
+    sim.int_registers[1] = i32::MAX;
+    operate(&mut sim).unwrap();
+    assert_eq!(sim.int_registers[1], i32::MIN);
+    assert!(sim.overflow);
+    sim.program_counter = 1;
+    operate(&mut sim).unwrap();
+    assert_eq!(sim.program_counter, 7);
+    sim.program_counter = 2;
+    operate(&mut sim).unwrap();
+    assert_eq!(sim.int_registers[1], i32::MAX);
+    assert!(sim.overflow);
+    sim.int_registers[1] = 5;
+    sim.program_counter = 2;
+    operate(&mut sim).unwrap();
+    assert!(!sim.overflow);
+  }
+
+  #[test]
+  fn min_by_minus_one_overflow_test() {
+    let mut sim = simulator(&[
+      Instructions::DIV(3, 1, 2),
+      Instructions::REM(4, 1, 2),
+      Instructions::NEG(5, 1),
+      Instructions::ABS(6, 1),
+      Instructions::BOV(String::from("@OVERFLOW")),
+    ]);
+    sim.labels.insert(String::from("@OVERFLOW"), 7);
+    sim.int_registers[1] = i32::MIN;
+    sim.int_registers[2] = -1;
+    for (n, reg, expected) in [
+      (0, 3, i32::MIN),
+      (1, 4, 0),
+      (2, 5, i32::MIN),
+      (3, 6, i32::MIN),
+    ] {
+      sim.overflow = false;
+      sim.program_counter = n;
+      operate(&mut sim).unwrap();
+      assert_eq!(sim.int_registers[reg], expected);
+      assert!(sim.overflow);
+    }
+    sim.program_counter = 4;
+    operate(&mut sim).unwrap();
+    assert_eq!(sim.program_counter, 7);
+
+    sim.int_registers[1] = 7;
+    for n in 0..4 {
+      sim.program_counter = n;
+      operate(&mut sim).unwrap();
+      assert!(!sim.overflow);
+    }
+  }
+} // mod operation_test
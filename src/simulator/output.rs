@@ -0,0 +1,92 @@
+//! Author: TheLazyFerret (https://github.com/TheLazyFerret)
+//! Copyright (c) 2025 TheLazyFerret
+//! Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//!
+//! output sink related module
+
+use std::sync::mpsc::Sender;
+
+use crate::simulator::Error;
+
+/// Where the output of the program is written.
+#[derive(Debug, Default)]
+pub enum Output {
+  /// Write directly to the standard output (default).
+  #[default]
+  Stdout,
+  /// Store everything in a buffer, retrievable with `Simulator::take_output`.
+  Captured(String),
+  /// Discard everything, set with `Simulator::with_null_output`.
+  Null,
+  /// Send every write as a `PrintEvent`, set with `Simulator::with_output_channel`.
+  Channel(Sender<PrintEvent>),
+}
+
+/// What the program printed, as sent through an output channel.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PrintEvent {
+  /// A register printed by PRINT, with its raw value.
+  Value { register: usize, value: i32 },
+  /// Any other text (PRINTNN, PRINTLN, PRINTREGS).
+  Text(String),
+}
+
+/// How the register values are written.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ValueFormat {
+  /// Signed decimal, like `-1` (default).
+  #[default]
+  Dec,
+  /// Hexadecimal of the 32 bits, like `0xffffffff`.
+  Hex,
+}
+
+impl ValueFormat {
+  /// Returns the value written in this format.
+  pub fn format(&self, value: i32) -> String {
+    match self {
+      ValueFormat::Dec => value.to_string(),
+      ValueFormat::Hex => format!("{:#x}", value as u32),
+    }
+  }
+}
+
+impl Output {
+  /// Writes the text in the sink.
+  pub fn write(&mut self, text: &str) -> Result<(), Error> {
+    match self {
+      Output::Stdout => print!("{text}"),
+      Output::Captured(buffer) => buffer.push_str(text),
+      Output::Null => (),
+      Output::Channel(sender) => {
+        return sender
+          .send(PrintEvent::Text(text.to_owned()))
+          .map_err(|_| Error::OutputClosed);
+      }
+    }
+    Ok(())
+  }
+
+  /// Writes the text of a printed register, or sends its value if the output is a channel.
+  pub fn write_value(&mut self, register: usize, value: i32, text: &str) -> Result<(), Error> {
+    match self {
+      Output::Channel(sender) => sender
+        .send(PrintEvent::Value { register, value })
+        .map_err(|_| Error::OutputClosed),
+      _ => self.write(text),
+    }
+  }
+
+  /// Returns whether everything written is discarded, so there is no need to format it.
+  pub fn is_null(&self) -> bool {
+    matches!(self, Output::Null)
+  }
+
+  /// Returns and clears the captured text. Empty if the output is not captured.
+  pub fn take(&mut self) -> String {
+    match self {
+      Output::Captured(buffer) => std::mem::take(buffer),
+      _ => String::new(),
+    }
+  }
+}
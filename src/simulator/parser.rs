@@ -3,62 +3,316 @@
 //! Licensed under the MIT License. See LICENSE file in the project root for full license information.
 //!
 //! Parsing related module
+//!
+//! Whitespace policy: mnemonic and operands are separated by one or more spaces or tabs,
+//! and any leading or trailing whitespace in the line is ignored.
 
 use regex::Regex;
 use std::sync::LazyLock;
 
-use crate::simulator::{Error, Instructions, Simulator};
+use crate::simulator::{Error, Instructions, MAX_PROGRAM_SIZE, Simulator};
 
 static INSTRUCTION_PARSER: LazyLock<Regex> =
-  LazyLock::new(|| Regex::new(r"^\s*([A-Z]+)(?:\s+.*)*$").unwrap());
+  LazyLock::new(|| Regex::new(r"^\s*([A-Z]+)(?:\s+.*)?$").unwrap());
 static LI_PARSER: LazyLock<Regex> =
-  LazyLock::new(|| Regex::new(r"^\s*(?:LI)\s+\$(\d+)\s+(-?\d+)\s*$").unwrap());
-static MOVE_PARSER: LazyLock<Regex> =
-  LazyLock::new(|| Regex::new(r"^\s*(?:MOVE)\s+\$(\d+)\s+\$(\d+)\s*$").unwrap());
+  LazyLock::new(|| Regex::new(r"^\s*(?:LI)\s+\$(\d+)\s+([-+*/()\d\s]+?)\s*$").unwrap());
+static IMMEDIATE_PARSER: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(r"^\s*(?:ADDI|SUBI)\s+\$(\d+)\s+\$(\d+)\s+([-+*/()\d\s]+?)\s*$").unwrap()
+});
+static MOVE_PARSER: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(r"^\s*(?:MOVE|BSWAP|RBIT|NOT|NEG|SWAP|ABS)\s+\$(\d+)\s+\$(\d+)\s*$").unwrap()
+});
 static ARITHMETIC_PARSER: LazyLock<Regex> = LazyLock::new(|| {
-  Regex::new(r"^\s*(?:ADD|SUB|MUL|DIV|REM)\s+\$(\d+)\s+\$(\d+)\s+\$(\d+)\s*$").unwrap()
+  Regex::new(r"^\s*(?:ADD|SUB|MUL|DIV|REM|ABSDIFF|SETEQ|SETNE|SETLT|SETLE|SETGT|SETGE|GCD|AND|OR|XOR|SHL|SHR|SLT|MIN|MAX)\s+\$(\d+)\s+\$(\d+)\s+\$(\d+)\s*$").unwrap()
 });
 static PRINT_PARSER: LazyLock<Regex> =
-  LazyLock::new(|| Regex::new(r"^\s*(?:PRINT)\s+\$(\d+)\s*$").unwrap());
-static JUMP_PARSER: LazyLock<Regex> =
-  LazyLock::new(|| Regex::new(r"^\s*(?:JUMP)\s+(@[A-Z]+)\s*$").unwrap());
+  LazyLock::new(|| Regex::new(r"^\s*(?:PRINT|PRINTNN|PRINTC)\s+\$(\d+)\s*$").unwrap());
+static JUMP_PARSER: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(r"^\s*(?:JUMP|BOV|TAILCALL|CALL)\s+(@?[A-Za-z_][A-Za-z0-9_]*)\s*$").unwrap()
+});
 static COND_JUMP_PARSER: LazyLock<Regex> = LazyLock::new(|| {
-  Regex::new(r"^\s*(?:BEQ|BNE|BLT|BLE|BGT|BGE)\s+\$(\d+)\s+\$(\d+)\s+(@[A-Z]+)\s*$").unwrap()
+  Regex::new(
+    r"^\s*(?:BEQ|BNE|BLT|BLE|BGT|BGE|BLTU|BLEU|BGTU|BGEU)\s+\$(\d+)\s+\$(\d+)\s+(@?[A-Za-z_][A-Za-z0-9_]*)\s*$",
+  )
+  .unwrap()
 });
 static PUSH_PARSER: LazyLock<Regex> =
-  LazyLock::new(|| Regex::new(r"^\s*(?:PUSH|POP)\s+\$(\d+)\s*$").unwrap());
+  LazyLock::new(|| Regex::new(r"^\s*(?:PUSH|POP|SP|STEPS)\s+\$(\d+)\s*$").unwrap());
+static INCDEC_PARSER: LazyLock<Regex> =
+  LazyLock::new(|| Regex::new(r"^\s*(?:INC|DEC)\s+\$(\d+)\s*$").unwrap());
+static COND_SKIP_PARSER: LazyLock<Regex> =
+  LazyLock::new(|| Regex::new(r"^\s*(?:SKIPEQ|SKIPNE)\s+\$(\d+)\s+\$(\d+)\s*$").unwrap());
+static READALL_PARSER: LazyLock<Regex> =
+  LazyLock::new(|| Regex::new(r"^\s*(?:READALL)\s+\$(\d+)\s+(\d+)\s*$").unwrap());
+static VADD_PARSER: LazyLock<Regex> =
+  LazyLock::new(|| Regex::new(r"^\s*(?:VADD)\s+\$(\d+)\s+\$(\d+)\s+\$(\d+)\s+(\d+)\s*$").unwrap());
+static POWMOD_PARSER: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(r"^\s*(?:POWMOD)\s+\$(\d+)\s+\$(\d+)\s+\$(\d+)\s+\$(\d+)\s*$").unwrap()
+});
+
+/// Mnemonics of all the instructions accepted by the parser.
+pub const SUPPORTED_INSTRUCTIONS: &[&str] = &[
+  "LI",
+  "MOVE",
+  "ADD",
+  "SUB",
+  "MUL",
+  "DIV",
+  "REM",
+  "ABSDIFF",
+  "READALL",
+  "PRINT",
+  "JUMP",
+  "BEQ",
+  "BNE",
+  "BLT",
+  "BLE",
+  "BGT",
+  "BGE",
+  "BLTU",
+  "BLEU",
+  "BGTU",
+  "BGEU",
+  "PUSH",
+  "POP",
+  "SP",
+  "SKIPEQ",
+  "SKIPNE",
+  "SKIP",
+  "EXIT",
+  "VADD",
+  "BOV",
+  "CLO",
+  "PRINTREGS",
+  "POWMOD",
+  "PRINTNN",
+  "PRINTLN",
+  "BSWAP",
+  "RBIT",
+  "SETEQ",
+  "SETNE",
+  "SETLT",
+  "SETLE",
+  "SETGT",
+  "SETGE",
+  "TAILCALL",
+  "CALL",
+  "RET",
+  "ADDI",
+  "SUBI",
+  "GCD",
+  "AND",
+  "OR",
+  "XOR",
+  "NOT",
+  "NEG",
+  "SHL",
+  "SHR",
+  "SLT",
+  "STEPS",
+  "NOP",
+  "PRINTC",
+  "SWAP",
+  "MIN",
+  "MAX",
+  "ABS",
+  "INC",
+  "DEC",
+];
 
 const AVOID_PARSER: &str = r"^\s*(?:\/\/.*)?\s*$";
-const LABEL_PARSER: &str = r"^\s*@([A-Z]+)\s*$";
+const LABEL_PARSER: &str = r"^\s*(@[A-Za-z_][A-Za-z0-9_]*)(:?)\s*$";
+const LABEL_PREFIX_PARSER: &str = r"^\s*(@[A-Za-z_][A-Za-z0-9_]*):\s*(.+)$";
+const EXPECTED_OUTPUT_PARSER: &str = r"^\s*//!\s*OUTPUT:\s?(.*)$";
+const ORG_PARSER: &str = r"^\s*\.org\s+(\d+)\s*$";
+
+/// Lexical pieces of an instruction line.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Token {
+  Mnemonic(String),
+  Register(usize),
+  Immediate(i32),
+  Label(String),
+  Unknown(String),
+}
+
+/// Splits the line in tokens, without checking that they make a valid instruction.
+pub fn tokenize(line: &str) -> Vec<Token> {
+  let mut tokens = Vec::new();
+  for word in line.split_whitespace() {
+    let token = if let Some(reg) = word.strip_prefix('$').and_then(|x| x.parse().ok()) {
+      Token::Register(reg)
+    } else if let Ok(imm) = word.parse() {
+      Token::Immediate(imm)
+    } else if word.len() > 1
+      && word.starts_with('@')
+      && word[1..].chars().all(|x| x.is_ascii_uppercase())
+    {
+      Token::Label(word.to_owned())
+    } else if tokens.is_empty() && word.chars().all(|x| x.is_ascii_uppercase()) {
+      Token::Mnemonic(word.to_owned())
+    } else {
+      Token::Unknown(word.to_owned())
+    };
+    tokens.push(token);
+  }
+  tokens
+}
+
+/// Fails with the number (starting at 1) of the first line longer than max bytes.
+/// It runs before any regex, so huge lines never reach the parsers.
+pub fn check_line_length(lines: &[String], max: usize) -> Result<(), Error> {
+  match lines.iter().position(|x| x.len() > max) {
+    Some(index) => Err(Error::LineTooLong { line: index + 1 }),
+    None => Ok(()),
+  }
+}
 
-/// Returns a new Vec<String> with all comments and empty lines removed
+/// Returns the line without the trailing `\r`, and without the UTF-8 BOM if it is the first one.
+fn clean_line(index: usize, line: &str) -> &str {
+  let line = line.trim_end_matches('\r');
+  if index == 0 {
+    line.strip_prefix('\u{feff}').unwrap_or(line)
+  } else {
+    line
+  }
+}
+
+/// Returns a new Vec<String> with all comments and empty lines removed.
+/// Trailing `\r` are trimmed, so CRLF files are parsed like LF ones, and so is a leading BOM.
 pub fn preprocess_lines(lines: &[String]) -> Vec<String> {
+  preprocess_numbered_lines(lines)
+    .into_iter()
+    .map(|(_, n)| n)
+    .collect()
+}
+
+/// Like `preprocess_lines`, keeping the index of each remaining line in the source.
+pub fn preprocess_numbered_lines(lines: &[String]) -> Vec<(usize, String)> {
   let regex = Regex::new(AVOID_PARSER).expect("error compiling regex");
   let mut container = Vec::with_capacity(lines.len());
-  for n in lines {
+  for (index, n) in lines.iter().enumerate() {
+    let n = clean_line(index, n);
     if !regex.is_match(n) {
-      container.push(n.to_owned());
+      container.push((index, n.to_owned()));
     }
   }
   container
 }
 
-/// Parse and fill the sim.instructions and sim.labels
+/// Returns a new Vec<String> where comments and empty lines are replaced by SKIP,
+/// keeping the comment text so process_lines stores it in sim.comments.
+pub fn preserve_comment_lines(lines: &[String]) -> Vec<String> {
+  let regex = Regex::new(AVOID_PARSER).expect("error compiling regex");
+  lines
+    .iter()
+    .enumerate()
+    .map(|(index, n)| {
+      let n = clean_line(index, n);
+      if regex.is_match(n) {
+        format!("SKIP {}", n.trim())
+      } else {
+        n.to_owned()
+      }
+    })
+    .collect()
+}
+
+/// Returns the expected output lines, declared with `//! OUTPUT: ...` comments.
+pub fn collect_expected_output(lines: &[String]) -> Vec<String> {
+  let regex = Regex::new(EXPECTED_OUTPUT_PARSER).expect("error compiling regex");
+  lines
+    .iter()
+    .filter_map(|x| regex.captures(x.trim_end_matches('\r')))
+    .map(|x| x[1].to_owned())
+    .collect()
+}
+
+/// Parse and fill the sim.instructions and sim.labels.
+/// Inline comments (`// ...`) are removed from the code and kept in sim.comments.
 pub fn process_lines(lines: &[String], sim: &mut Simulator) -> Result<(), Error> {
   let label_parser = Regex::new(LABEL_PARSER).expect("error compiling regex");
-  for n in lines.iter().enumerate() {
-    if label_parser.is_match(n.1) {
+  let label_prefix_parser = Regex::new(LABEL_PREFIX_PARSER).expect("error compiling regex");
+  let org_parser = Regex::new(ORG_PARSER).expect("error compiling regex");
+  for n in lines {
+    let n = match n.split_once("//") {
+      Some((code, comment)) => {
+        if !comment.trim().is_empty() {
+          let index = sim.instructions.len();
+          sim.comments.insert(index, comment.trim().to_owned());
+        }
+        code
+      }
+      None => n.as_str(),
+    };
+    if let Some(capt) = label_parser.captures(n) {
+      if sim.label_colons && capt[2].is_empty() {
+        return Err(Error::MissingLabelColon);
+      }
+      sim
+        .labels
+        .insert(normalize_label(&capt[1]), sim.instructions.len());
+      sim.instructions.push(Instructions::LABEL);
+    } else if let Some(capt) = label_prefix_parser.captures(n) {
+      // Like a label in its own line, followed by the instruction, as jumps continue after the label.
+      let instruction = parse_instruction(&capt[2])?;
+      let index = sim.instructions.len();
+      sim.labels.insert(normalize_label(&capt[1]), index);
       sim.instructions.push(Instructions::LABEL);
-      sim.labels.insert(n.1.to_owned(), n.0);
+      sim.instructions.push(instruction);
+      if let Some(comment) = sim.comments.remove(&index) {
+        sim.comments.insert(index + 1, comment);
+      }
+    } else if let Some(capt) = org_parser.captures(n) {
+      let origin: usize = capt[1].parse().map_err(|_| Error::InvalidParameter)?;
+      if origin < sim.instructions.len() || origin > MAX_PROGRAM_SIZE {
+        return Err(Error::InvalidParameter);
+      }
+      sim.instructions.resize(origin, Instructions::SKIP);
     } else {
-      sim.instructions.push(parse_instruction(n.1)?);
+      sim.instructions.push(parse_instruction(n)?);
     }
   }
   Ok(())
 }
 
-/// Parse each instruction, returning a Instruction or the type of Error
+/// Returns the canonical form of a label: trimmed, in caps and with the `@` prefix.
+pub fn normalize_label(label: &str) -> String {
+  format!("@{}", label.trim().trim_start_matches('@').to_uppercase())
+}
+
+/// Parse each instruction, returning a Instruction or the type of Error.
+/// Operands followed by anything else are an `Error::UnexpectedToken` with the first extra token.
+/// A well-formed expression whose value can't be computed is an `Error::InvalidParameter` instead.
 pub fn parse_instruction(line: &str) -> Result<Instructions, Error> {
+  match parse_operands(line) {
+    Err(Error::InvalidParameter) if !has_expression(line) => Err(unexpected_token(line)),
+    result => result,
+  }
+}
+
+/// Whether the operands of the line end with a well-formed expression, like the immediate of LI.
+fn has_expression(line: &str) -> bool {
+  LI_PARSER
+    .captures(line)
+    .or_else(|| IMMEDIATE_PARSER.captures(line))
+    .and_then(|capt| capt.iter().last().flatten())
+    .is_some_and(|expr| is_expression(expr.as_str()))
+}
+
+/// Returns the token after the longest valid start of the line, or `InvalidParameter` if there is none.
+fn unexpected_token(line: &str) -> Error {
+  let tokens: Vec<&str> = line.split_whitespace().collect();
+  for end in (1..tokens.len()).rev() {
+    if parse_operands(&tokens[..end].join(" ")).is_ok() {
+      return Error::UnexpectedToken(tokens[end].to_owned());
+    }
+  }
+  Error::InvalidParameter
+}
+
+/// Parse the instruction and its operands, by its mnemonic.
+fn parse_operands(line: &str) -> Result<Instructions, Error> {
   let inst = INSTRUCTION_PARSER
     .captures(line)
     .ok_or(Error::InvalidInstruction)?;
@@ -91,10 +345,31 @@ pub fn parse_instruction(line: &str) -> Result<Instructions, Error> {
       let params = parse_arithmetic(line)?;
       Ok(Instructions::REM(params.0, params.1, params.2))
     }
+    "ABSDIFF" => {
+      let params = parse_arithmetic(line)?;
+      Ok(Instructions::ABSDIFF(params.0, params.1, params.2))
+    }
+    "VADD" => {
+      let params = parse_vadd(line)?;
+      Ok(Instructions::VADD(params.0, params.1, params.2, params.3))
+    }
+    "POWMOD" => {
+      let params = parse_powmod(line)?;
+      Ok(Instructions::POWMOD(params.0, params.1, params.2, params.3))
+    }
+    "READALL" => {
+      let params = parse_readall(line)?;
+      Ok(Instructions::READALL(params.0, params.1))
+    }
     "PRINT" => {
       let params = parse_print(line)?;
       Ok(Instructions::PRINT(params))
     }
+    "PRINTNN" => {
+      let params = parse_print(line)?;
+      Ok(Instructions::PRINTNN(params))
+    }
+    "PRINTLN" => parse_no_operands(line, Instructions::PRINTLN),
     "JUMP" => {
       let params = parse_jump(line)?;
       Ok(Instructions::JUMP(params))
@@ -123,6 +398,22 @@ pub fn parse_instruction(line: &str) -> Result<Instructions, Error> {
       let param = parser_cond_jump(line)?;
       Ok(Instructions::BGE(param.0, param.1, param.2))
     }
+    "BLTU" => {
+      let param = parser_cond_jump(line)?;
+      Ok(Instructions::BLTU(param.0, param.1, param.2))
+    }
+    "BLEU" => {
+      let param = parser_cond_jump(line)?;
+      Ok(Instructions::BLEU(param.0, param.1, param.2))
+    }
+    "BGTU" => {
+      let param = parser_cond_jump(line)?;
+      Ok(Instructions::BGTU(param.0, param.1, param.2))
+    }
+    "BGEU" => {
+      let param = parser_cond_jump(line)?;
+      Ok(Instructions::BGEU(param.0, param.1, param.2))
+    }
     "PUSH" => {
       let param = parser_stack(line)?;
       Ok(Instructions::PUSH(param))
@@ -131,75 +422,453 @@ pub fn parse_instruction(line: &str) -> Result<Instructions, Error> {
       let param = parser_stack(line)?;
       Ok(Instructions::POP(param))
     }
-    "SKIP" => Ok(Instructions::SKIP),
-    "EXIT" => Ok(Instructions::EXIT),
+    "SP" => {
+      let param = parser_stack(line)?;
+      Ok(Instructions::SP(param))
+    }
+    "SKIPEQ" => {
+      let param = parser_cond_skip(line)?;
+      Ok(Instructions::SKIPEQ(param.0, param.1))
+    }
+    "SKIPNE" => {
+      let param = parser_cond_skip(line)?;
+      Ok(Instructions::SKIPNE(param.0, param.1))
+    }
+    "BOV" => {
+      let params = parse_jump(line)?;
+      Ok(Instructions::BOV(params))
+    }
+    "CLO" => parse_no_operands(line, Instructions::CLO),
+    "PRINTREGS" => parse_no_operands(line, Instructions::PRINTREGS),
+    "SKIP" => parse_no_operands(line, Instructions::SKIP),
+    "EXIT" => parse_no_operands(line, Instructions::EXIT),
+    "BSWAP" => {
+      let param = parse_move(line)?;
+      Ok(Instructions::BSWAP(param.0, param.1))
+    }
+    "RBIT" => {
+      let param = parse_move(line)?;
+      Ok(Instructions::RBIT(param.0, param.1))
+    }
+    "SETEQ" => {
+      let params = parse_arithmetic(line)?;
+      Ok(Instructions::SETEQ(params.0, params.1, params.2))
+    }
+    "SETNE" => {
+      let params = parse_arithmetic(line)?;
+      Ok(Instructions::SETNE(params.0, params.1, params.2))
+    }
+    "SETLT" => {
+      let params = parse_arithmetic(line)?;
+      Ok(Instructions::SETLT(params.0, params.1, params.2))
+    }
+    "SETLE" => {
+      let params = parse_arithmetic(line)?;
+      Ok(Instructions::SETLE(params.0, params.1, params.2))
+    }
+    "SETGT" => {
+      let params = parse_arithmetic(line)?;
+      Ok(Instructions::SETGT(params.0, params.1, params.2))
+    }
+    "SETGE" => {
+      let params = parse_arithmetic(line)?;
+      Ok(Instructions::SETGE(params.0, params.1, params.2))
+    }
+    "TAILCALL" => {
+      let params = parse_jump(line)?;
+      Ok(Instructions::TAILCALL(params))
+    }
+    "CALL" => {
+      let params = parse_jump(line)?;
+      Ok(Instructions::CALL(params))
+    }
+    "RET" => parse_no_operands(line, Instructions::RET),
+    "ADDI" => {
+      let params = parse_immediate(line)?;
+      Ok(Instructions::ADDI(params.0, params.1, params.2))
+    }
+    "SUBI" => {
+      let params = parse_immediate(line)?;
+      Ok(Instructions::SUBI(params.0, params.1, params.2))
+    }
+    "GCD" => {
+      let params = parse_arithmetic(line)?;
+      Ok(Instructions::GCD(params.0, params.1, params.2))
+    }
+    "AND" => {
+      let params = parse_arithmetic(line)?;
+      Ok(Instructions::AND(params.0, params.1, params.2))
+    }
+    "OR" => {
+      let params = parse_arithmetic(line)?;
+      Ok(Instructions::OR(params.0, params.1, params.2))
+    }
+    "XOR" => {
+      let params = parse_arithmetic(line)?;
+      Ok(Instructions::XOR(params.0, params.1, params.2))
+    }
+    "NOT" => {
+      let param = parse_move(line)?;
+      Ok(Instructions::NOT(param.0, param.1))
+    }
+    "NEG" => {
+      let param = parse_move(line)?;
+      Ok(Instructions::NEG(param.0, param.1))
+    }
+    "SHL" => {
+      let params = parse_arithmetic(line)?;
+      Ok(Instructions::SHL(params.0, params.1, params.2))
+    }
+    "SHR" => {
+      let params = parse_arithmetic(line)?;
+      Ok(Instructions::SHR(params.0, params.1, params.2))
+    }
+    "SLT" => {
+      let params = parse_arithmetic(line)?;
+      Ok(Instructions::SLT(params.0, params.1, params.2))
+    }
+    "STEPS" => {
+      let param = parser_stack(line)?;
+      Ok(Instructions::STEPS(param))
+    }
+    "NOP" => parse_no_operands(line, Instructions::NOP),
+    "PRINTC" => {
+      let params = parse_print(line)?;
+      Ok(Instructions::PRINTC(params))
+    }
+    "SWAP" => {
+      let param = parse_move(line)?;
+      Ok(Instructions::SWAP(param.0, param.1))
+    }
+    "MIN" => {
+      let params = parse_arithmetic(line)?;
+      Ok(Instructions::MIN(params.0, params.1, params.2))
+    }
+    "MAX" => {
+      let params = parse_arithmetic(line)?;
+      Ok(Instructions::MAX(params.0, params.1, params.2))
+    }
+    "ABS" => {
+      let param = parse_move(line)?;
+      Ok(Instructions::ABS(param.0, param.1))
+    }
+    "INC" => {
+      let param = parser_incdec(line)?;
+      Ok(Instructions::INC(param))
+    }
+    "DEC" => {
+      let param = parser_incdec(line)?;
+      Ok(Instructions::DEC(param))
+    }
     _ => Err(Error::InvalidInstruction),
   }
-} // fn parse_instruction
+} // fn parse_operands
+
+/// Parse an instruction without operands, rejecting anything after the mnemonic.
+fn parse_no_operands(line: &str, instruction: Instructions) -> Result<Instructions, Error> {
+  if line.split_whitespace().count() == 1 {
+    Ok(instruction)
+  } else {
+    Err(Error::InvalidParameter)
+  }
+}
 
 /// Parse a LI instruction.
 fn parse_li(line: &str) -> Result<(usize, i32), Error> {
   let capt = LI_PARSER.captures(line).ok_or(Error::InvalidParameter)?;
-  let a: usize = capt[1].parse().expect("error parsing");
-  let b: i32 = capt[2].parse().expect("error parsing");
+  let a: usize = capt[1].parse().map_err(|_| Error::InvalidParameter)?;
+  let b: i32 = evaluate_expression(&capt[2])?;
   Ok((a, b))
 }
 
-/// Parse a MOVE instruction
+/// Parse a register and immediate (ADDI, SUBI) instruction. The immediate is an expression like in LI.
+fn parse_immediate(line: &str) -> Result<(usize, usize, i32), Error> {
+  let capt = IMMEDIATE_PARSER
+    .captures(line)
+    .ok_or(Error::InvalidParameter)?;
+  let a: usize = capt[1].parse().map_err(|_| Error::InvalidParameter)?;
+  let b: usize = capt[2].parse().map_err(|_| Error::InvalidParameter)?;
+  let c: i32 = evaluate_expression(&capt[3])?;
+  Ok((a, b, c))
+}
+
+/// Evaluates a constant expression of integer literals with `+ - * / ( )` and the usual precedence,
+/// using the shunting-yard algorithm. Overflows and divisions by zero are `InvalidParameter`.
+fn evaluate_expression(expr: &str) -> Result<i32, Error> {
+  let value = reduce_expression(expr, |x| x.parse().ok(), apply_operator)?;
+  i32::try_from(value).map_err(|_| Error::InvalidParameter)
+}
+
+/// Whether the expression is well-formed, whatever the values of its literals and operations.
+fn is_expression(expr: &str) -> bool {
+  let apply = |output: &mut Vec<i64>, op: char| {
+    let operands = if op == 'u' { 1 } else { 2 };
+    let len = output
+      .len()
+      .checked_sub(operands)
+      .ok_or(Error::InvalidParameter)?;
+    output.truncate(len);
+    output.push(0);
+    Ok(())
+  };
+  reduce_expression(expr, |_| Some(0), apply).is_ok()
+}
+
+/// Runs the shunting-yard algorithm over the expression, with the given literal parser and operator.
+fn reduce_expression(
+  expr: &str, literal: impl Fn(&str) -> Option<i64>,
+  apply_operator: impl Fn(&mut Vec<i64>, char) -> Result<(), Error>,
+) -> Result<i64, Error> {
+  let mut output: Vec<i64> = Vec::new();
+  let mut operators: Vec<char> = Vec::new();
+  let mut expect_operand = true;
+  let mut chars = expr.chars().peekable();
+  while let Some(c) = chars.next() {
+    match c {
+      _ if c.is_whitespace() => (),
+      '0'..='9' if expect_operand => {
+        let mut text = String::from(c);
+        while let Some(d) = chars.next_if(|d| d.is_ascii_digit()) {
+          text.push(d);
+        }
+        output.push(literal(&text).ok_or(Error::InvalidParameter)?);
+        expect_operand = false;
+      }
+      '(' if expect_operand => operators.push(c),
+      '-' if expect_operand => operators.push('u'),
+      ')' if !expect_operand => loop {
+        match operators.pop() {
+          Some('(') => break,
+          Some(op) => apply_operator(&mut output, op)?,
+          None => return Err(Error::InvalidParameter),
+        }
+      },
+      '+' | '-' | '*' | '/' if !expect_operand => {
+        while let Some(&top) = operators.last() {
+          if top == '(' || precedence(top) < precedence(c) {
+            break;
+          }
+          apply_operator(&mut output, top)?;
+          operators.pop();
+        }
+        operators.push(c);
+        expect_operand = true;
+      }
+      _ => return Err(Error::InvalidParameter),
+    }
+  }
+  if expect_operand {
+    return Err(Error::InvalidParameter);
+  }
+  while let Some(op) = operators.pop() {
+    if op == '(' {
+      return Err(Error::InvalidParameter);
+    }
+    apply_operator(&mut output, op)?;
+  }
+  match output[..] {
+    [value] => Ok(value),
+    _ => Err(Error::InvalidParameter),
+  }
+}
+
+/// Returns the precedence of an operator of an expression (`u` is the unary minus).
+fn precedence(op: char) -> u8 {
+  match op {
+    '+' | '-' => 1,
+    '*' | '/' => 2,
+    _ => 3,
+  }
+}
+
+/// Applies the operator to the operands at the top of the stack.
+fn apply_operator(output: &mut Vec<i64>, op: char) -> Result<(), Error> {
+  let b = output.pop().ok_or(Error::InvalidParameter)?;
+  let result = if op == 'u' {
+    Some(-b)
+  } else {
+    let a = output.pop().ok_or(Error::InvalidParameter)?;
+    match op {
+      '+' => a.checked_add(b),
+      '-' => a.checked_sub(b),
+      '*' => a.checked_mul(b),
+      _ => a.checked_div(b),
+    }
+  };
+  let result = result
+    .filter(|x| i32::try_from(*x).is_ok())
+    .ok_or(Error::InvalidParameter)?;
+  output.push(result);
+  Ok(())
+}
+
+/// Parse a two registers instruction, like `MOVE $1 $2`.
 fn parse_move(line: &str) -> Result<(usize, usize), Error> {
   let capt = MOVE_PARSER.captures(line).ok_or(Error::InvalidParameter)?;
-  let a: usize = capt[1].parse().expect("error parsing");
-  let b: usize = capt[2].parse().expect("error parsing");
+  let a: usize = capt[1].parse().map_err(|_| Error::InvalidParameter)?;
+  let b: usize = capt[2].parse().map_err(|_| Error::InvalidParameter)?;
   Ok((a, b))
 }
 
-/// Parse a arithmetic (ADD, SUB, MUL, DIV, REM) instruction.
+/// Parse a three registers instruction, like `ADD $1 $2 $3`.
 fn parse_arithmetic(line: &str) -> Result<(usize, usize, usize), Error> {
   let capt = ARITHMETIC_PARSER
     .captures(line)
     .ok_or(Error::InvalidParameter)?;
-  let a: usize = capt[1].parse().expect("error parsing");
-  let b: usize = capt[2].parse().expect("error parsing");
-  let c: usize = capt[3].parse().expect("error parsing");
+  let a: usize = capt[1].parse().map_err(|_| Error::InvalidParameter)?;
+  let b: usize = capt[2].parse().map_err(|_| Error::InvalidParameter)?;
+  let c: usize = capt[3].parse().map_err(|_| Error::InvalidParameter)?;
   Ok((a, b, c))
 }
 
-/// Parse a PRINT instruction.
+/// Parse a PRINT, PRINTNN or PRINTC instruction.
 fn parse_print(line: &str) -> Result<usize, Error> {
   let capt = PRINT_PARSER.captures(line).ok_or(Error::InvalidParameter)?;
-  let a: usize = capt[1].parse().expect("error parsing");
+  let a: usize = capt[1].parse().map_err(|_| Error::InvalidParameter)?;
   Ok(a)
 }
 
-/// Parse a JUMP instruction.
+/// Parse a VADD instruction.
+fn parse_vadd(line: &str) -> Result<(usize, usize, usize, usize), Error> {
+  let capt = VADD_PARSER.captures(line).ok_or(Error::InvalidParameter)?;
+  let a: usize = capt[1].parse().map_err(|_| Error::InvalidParameter)?;
+  let b: usize = capt[2].parse().map_err(|_| Error::InvalidParameter)?;
+  let c: usize = capt[3].parse().map_err(|_| Error::InvalidParameter)?;
+  let d: usize = capt[4].parse().map_err(|_| Error::InvalidParameter)?;
+  Ok((a, b, c, d))
+}
+
+/// Parse a POWMOD instruction.
+fn parse_powmod(line: &str) -> Result<(usize, usize, usize, usize), Error> {
+  let capt = POWMOD_PARSER
+    .captures(line)
+    .ok_or(Error::InvalidParameter)?;
+  let a: usize = capt[1].parse().map_err(|_| Error::InvalidParameter)?;
+  let b: usize = capt[2].parse().map_err(|_| Error::InvalidParameter)?;
+  let c: usize = capt[3].parse().map_err(|_| Error::InvalidParameter)?;
+  let d: usize = capt[4].parse().map_err(|_| Error::InvalidParameter)?;
+  Ok((a, b, c, d))
+}
+
+/// Parse a READALL instruction.
+fn parse_readall(line: &str) -> Result<(usize, usize), Error> {
+  let capt = READALL_PARSER
+    .captures(line)
+    .ok_or(Error::InvalidParameter)?;
+  let a: usize = capt[1].parse().map_err(|_| Error::InvalidParameter)?;
+  let b: usize = capt[2].parse().map_err(|_| Error::InvalidParameter)?;
+  Ok((a, b))
+}
+
+/// Parse a JUMP, BOV, TAILCALL or CALL instruction.
 fn parse_jump(line: &str) -> Result<String, Error> {
   let capt = JUMP_PARSER.captures(line).ok_or(Error::InvalidParameter)?;
-  Ok(capt[1].to_owned())
+  Ok(normalize_label(&capt[1]))
 }
 
-/// Parse conditional jump (BGE, BGT, BLT, BLE, BGT, BGE) instruction.
+/// Parse conditional jump (BEQ, BNE, BLT, BLE, BGT, BGE and their unsigned variants) instruction.
 fn parser_cond_jump(line: &str) -> Result<(usize, usize, String), Error> {
   let capt = COND_JUMP_PARSER
     .captures(line)
     .ok_or(Error::InvalidParameter)?;
-  let a: usize = capt[1].parse().expect("error parsing");
-  let b: usize = capt[2].parse().expect("error parsing");
-  Ok((a, b, capt[3].to_owned()))
+  let a: usize = capt[1].parse().map_err(|_| Error::InvalidParameter)?;
+  let b: usize = capt[2].parse().map_err(|_| Error::InvalidParameter)?;
+  Ok((a, b, normalize_label(&capt[3])))
 }
 
-/// Parse stack instructions (PUSH and POP)
+/// Parse single register instructions (PUSH, POP, SP and STEPS)
 fn parser_stack(line: &str) -> Result<usize, Error> {
   let capt = PUSH_PARSER.captures(line).ok_or(Error::InvalidParameter)?;
-  let a: usize = capt[1].parse().expect("error parsing");
+  let a: usize = capt[1].parse().map_err(|_| Error::InvalidParameter)?;
   Ok(a)
 }
 
+/// Parse in place increment instructions (INC, DEC)
+fn parser_incdec(line: &str) -> Result<usize, Error> {
+  let capt = INCDEC_PARSER
+    .captures(line)
+    .ok_or(Error::InvalidParameter)?;
+  let a: usize = capt[1].parse().map_err(|_| Error::InvalidParameter)?;
+  Ok(a)
+}
+
+/// Parse conditional skip (SKIPEQ, SKIPNE) instructions.
+fn parser_cond_skip(line: &str) -> Result<(usize, usize), Error> {
+  let capt = COND_SKIP_PARSER
+    .captures(line)
+    .ok_or(Error::InvalidParameter)?;
+  let a: usize = capt[1].parse().map_err(|_| Error::InvalidParameter)?;
+  let b: usize = capt[2].parse().map_err(|_| Error::InvalidParameter)?;
+  Ok((a, b))
+}
+
 #[cfg(test)]
 mod parse_test {
   use crate::{
-    simulator::parser::{parse_instruction, process_lines},
-    simulator::{Instructions, Simulator},
+    simulator::parser::{
+      SUPPORTED_INSTRUCTIONS, Token, parse_instruction, preprocess_lines, process_lines, tokenize,
+    },
+    simulator::{Error, Instructions, Simulator},
   };
+  #[test]
+  fn supported_instructions_test() {
+    for mnemonic in SUPPORTED_INSTRUCTIONS {
+      assert_ne!(parse_instruction(mnemonic), Err(Error::InvalidInstruction));
+    }
+  }
+
+  #[test]
+  fn parse_irregular_spacing_test() {
+    let cases = [
+      ("LI   $1 \t 5 ", Instructions::LI(1, 5)),
+      ("MOVE  $1   $2", Instructions::MOVE(1, 2)),
+      ("ADD  $1   $2  $3", Instructions::ADD(1, 2, 3)),
+      ("SUB\t$1 $2\t\t$3", Instructions::SUB(1, 2, 3)),
+      ("MUL $1  $2 $3  ", Instructions::MUL(1, 2, 3)),
+      ("DIV  $1 $2   $3", Instructions::DIV(1, 2, 3)),
+      ("REM $1   $2 $3", Instructions::REM(1, 2, 3)),
+      ("ABSDIFF   $1 $2  $3", Instructions::ABSDIFF(1, 2, 3)),
+      ("VADD $1  $2   $3    4", Instructions::VADD(1, 2, 3, 4)),
+      ("READALL  $1   2 ", Instructions::READALL(1, 2)),
+      (
+        "BEQ  $1   $2  @L",
+        Instructions::BEQ(1, 2, String::from("@L")),
+      ),
+      (
+        "BGEU $1\t$2   @L",
+        Instructions::BGEU(1, 2, String::from("@L")),
+      ),
+      ("SKIPEQ   $1  $2", Instructions::SKIPEQ(1, 2)),
+      ("SKIPNE $1    $2 ", Instructions::SKIPNE(1, 2)),
+    ];
+    for (line, expected) in cases {
+      assert_eq!(parse_instruction(line), Ok(expected), "{line}");
+    }
+  }
+
+  #[test]
+  fn tokenize_test() {
+    assert_eq!(
+      tokenize("ADD $1 $2 $3"),
+      vec![
+        Token::Mnemonic(String::from("ADD")),
+        Token::Register(1),
+        Token::Register(2),
+        Token::Register(3),
+      ]
+    );
+    assert_eq!(
+      tokenize("  LI $4 -7 @END $x"),
+      vec![
+        Token::Mnemonic(String::from("LI")),
+        Token::Register(4),
+        Token::Immediate(-7),
+        Token::Label(String::from("@END")),
+        Token::Unknown(String::from("$x")),
+      ]
+    );
+  }
+
   #[test]
   fn parse_li_test() {
     let line: &str = "LI $64 -6";
@@ -207,6 +876,85 @@ mod parse_test {
     assert_eq!(x, Instructions::LI(64, -6));
   }
 
+  #[test]
+  fn parse_li_expression_test() {
+    assert_eq!(
+      parse_instruction("LI $1 2+3*4"),
+      Ok(Instructions::LI(1, 14))
+    );
+    assert_eq!(
+      parse_instruction("LI $1 (2 + 3) * 4"),
+      Ok(Instructions::LI(1, 20))
+    );
+    assert_eq!(
+      parse_instruction("LI $1 10-4-3"),
+      Ok(Instructions::LI(1, 3))
+    );
+    assert_eq!(
+      parse_instruction("LI $1 -(7/2)*-2"),
+      Ok(Instructions::LI(1, 6))
+    );
+    assert_eq!(
+      parse_instruction("LI $1 -2147483648"),
+      Ok(Instructions::LI(1, i32::MIN))
+    );
+  }
+
+  #[test]
+  fn parse_li_expression_error_test() {
+    assert_eq!(parse_instruction("LI $1 1/0"), Err(Error::InvalidParameter));
+    assert_eq!(
+      parse_instruction("LI $1 2147483647+1"),
+      Err(Error::InvalidParameter)
+    );
+    assert_eq!(
+      parse_instruction("LI $1 (2+3"),
+      Err(Error::InvalidParameter)
+    );
+    assert_eq!(
+      parse_instruction("LI $1 2 3"),
+      Err(Error::UnexpectedToken(String::from("3")))
+    );
+    assert_eq!(parse_instruction("LI $1 2*"), Err(Error::InvalidParameter));
+  }
+
+  #[test]
+  fn parse_spaced_expression_error_test() {
+    assert_eq!(
+      parse_instruction("LI $1 2147483647 + 1"),
+      Err(Error::InvalidParameter)
+    );
+    assert_eq!(
+      parse_instruction("LI $1 2 + 3 / 0"),
+      Err(Error::InvalidParameter)
+    );
+    assert_eq!(
+      parse_instruction("LI $1 99999999999999999999 * 0"),
+      Err(Error::InvalidParameter)
+    );
+    assert_eq!(
+      parse_instruction("ADDI $1 $2 1 - -2147483647 - 2"),
+      Err(Error::InvalidParameter)
+    );
+  }
+
+  #[test]
+  fn parse_immediate_test() {
+    assert_eq!(
+      parse_instruction("ADDI $1 $2 -3"),
+      Ok(Instructions::ADDI(1, 2, -3))
+    );
+    assert_eq!(
+      parse_instruction("SUBI $1 $2 4*2"),
+      Ok(Instructions::SUBI(1, 2, 8))
+    );
+    assert_eq!(parse_instruction("ADDI $1 3"), Err(Error::InvalidParameter));
+    assert_eq!(
+      parse_instruction("ADDI $1 $2 $3"),
+      Err(Error::InvalidParameter)
+    );
+  }
+
   #[test]
   fn parse_arith_test() {
     let line: &str = "ADD $64 $46 $24";
@@ -214,6 +962,59 @@ mod parse_test {
     assert_eq!(x, Instructions::ADD(64, 46, 24));
   }
 
+  #[test]
+  fn parse_absdiff_test() {
+    let x = parse_instruction("ABSDIFF $1 $2 $3").unwrap();
+    assert_eq!(x, Instructions::ABSDIFF(1, 2, 3));
+  }
+
+  #[test]
+  fn parse_vadd_test() {
+    let x = parse_instruction("VADD $10 $1 $4 3").unwrap();
+    assert_eq!(x, Instructions::VADD(10, 1, 4, 3));
+  }
+
+  #[test]
+  fn parse_bswap_rbit_test() {
+    assert_eq!(
+      parse_instruction("BSWAP $2 $1"),
+      Ok(Instructions::BSWAP(2, 1))
+    );
+    assert_eq!(
+      parse_instruction("RBIT $3 $1"),
+      Ok(Instructions::RBIT(3, 1))
+    );
+    assert_eq!(parse_instruction("RBIT $3"), Err(Error::InvalidParameter));
+  }
+
+  #[test]
+  fn parse_set_test() {
+    assert_eq!(
+      parse_instruction("SETEQ $3 $1 $2"),
+      Ok(Instructions::SETEQ(3, 1, 2))
+    );
+    assert_eq!(
+      parse_instruction("SETGE $3 $1 $2"),
+      Ok(Instructions::SETGE(3, 1, 2))
+    );
+    assert_eq!(
+      parse_instruction("SETLT $3 $1"),
+      Err(Error::InvalidParameter)
+    );
+  }
+
+  #[test]
+  fn parse_powmod_test() {
+    let x = parse_instruction("POWMOD $1 $2 $3 $4").unwrap();
+    assert_eq!(x, Instructions::POWMOD(1, 2, 3, 4));
+  }
+
+  #[test]
+  fn parse_readall_test() {
+    let x = parse_instruction("READALL $4 3").unwrap();
+    assert_eq!(x, Instructions::READALL(4, 3));
+  }
+
   #[test]
   fn parse_incon_test() {
     let line: &str = "JUMP @ENDLOOP";
@@ -221,6 +1022,108 @@ mod parse_test {
     assert_eq!(x, Instructions::JUMP(String::from("@ENDLOOP")));
   }
 
+  #[test]
+  fn label_normalization_test() {
+    let x = parse_instruction("JUMP @LOOP").unwrap();
+    assert_eq!(parse_instruction("JUMP @loop"), Ok(x.clone()));
+    assert_eq!(parse_instruction("  JUMP   Loop  "), Ok(x));
+    let x = parse_instruction("BEQ $1 $2 @end").unwrap();
+    assert_eq!(x, Instructions::BEQ(1, 2, String::from("@END")));
+
+    let mut simul = Simulator::new();
+    let lines: Vec<String> = vec![String::from("@Main:"), String::from("@loop: LI $1 1")];
+    process_lines(&lines, &mut simul).expect("error found");
+    assert_eq!(simul.labels["@MAIN"], 0);
+    assert_eq!(simul.labels["@LOOP"], 1);
+  }
+
+  #[test]
+  fn jump_round_trip_test() {
+    let x = parse_instruction("JUMP @LOOP").unwrap();
+    assert_eq!(x.to_string(), "JUMP @LOOP");
+    assert_eq!(parse_instruction(&x.to_string()), Ok(x));
+  }
+
+  /// One example of each variant but LABEL, which comes from the label lines.
+  fn every_instruction() -> Vec<Instructions> {
+    let label = || String::from("@LOOP");
+    vec![
+      Instructions::LI(1, -5),
+      Instructions::MOVE(1, 2),
+      Instructions::ADD(1, 2, 3),
+      Instructions::SUB(1, 2, 3),
+      Instructions::MUL(1, 2, 3),
+      Instructions::DIV(1, 2, 3),
+      Instructions::REM(1, 2, 3),
+      Instructions::PRINT(1),
+      Instructions::EXIT,
+      Instructions::SKIP,
+      Instructions::JUMP(label()),
+      Instructions::BEQ(1, 2, label()),
+      Instructions::BNE(1, 2, label()),
+      Instructions::BLT(1, 2, label()),
+      Instructions::BLE(1, 2, label()),
+      Instructions::BGT(1, 2, label()),
+      Instructions::BGE(1, 2, label()),
+      Instructions::BLTU(1, 2, label()),
+      Instructions::BLEU(1, 2, label()),
+      Instructions::BGTU(1, 2, label()),
+      Instructions::BGEU(1, 2, label()),
+      Instructions::PUSH(1),
+      Instructions::POP(1),
+      Instructions::SKIPEQ(1, 2),
+      Instructions::SKIPNE(1, 2),
+      Instructions::ABSDIFF(1, 2, 3),
+      Instructions::READALL(1, 4),
+      Instructions::SP(1),
+      Instructions::VADD(1, 2, 3, 4),
+      Instructions::BOV(label()),
+      Instructions::CLO,
+      Instructions::PRINTREGS,
+      Instructions::POWMOD(1, 2, 3, 4),
+      Instructions::PRINTNN(1),
+      Instructions::PRINTLN,
+      Instructions::BSWAP(1, 2),
+      Instructions::RBIT(1, 2),
+      Instructions::SETEQ(1, 2, 3),
+      Instructions::SETNE(1, 2, 3),
+      Instructions::SETLT(1, 2, 3),
+      Instructions::SETLE(1, 2, 3),
+      Instructions::SETGT(1, 2, 3),
+      Instructions::SETGE(1, 2, 3),
+      Instructions::TAILCALL(label()),
+      Instructions::CALL(label()),
+      Instructions::RET,
+      Instructions::ADDI(1, 2, -3),
+      Instructions::SUBI(1, 2, 3),
+      Instructions::GCD(1, 2, 3),
+      Instructions::AND(1, 2, 3),
+      Instructions::OR(1, 2, 3),
+      Instructions::XOR(1, 2, 3),
+      Instructions::NOT(1, 2),
+      Instructions::NEG(1, 2),
+      Instructions::SHL(1, 2, 3),
+      Instructions::SHR(1, 2, 3),
+      Instructions::SLT(1, 2, 3),
+      Instructions::STEPS(1),
+      Instructions::NOP,
+      Instructions::PRINTC(1),
+      Instructions::SWAP(1, 2),
+      Instructions::MIN(1, 2, 3),
+      Instructions::MAX(1, 2, 3),
+      Instructions::ABS(1, 2),
+      Instructions::INC(1),
+      Instructions::DEC(1),
+    ]
+  }
+
+  #[test]
+  fn display_round_trip_test() {
+    for x in every_instruction() {
+      assert_eq!(parse_instruction(&x.to_string()), Ok(x));
+    }
+  }
+
   #[test]
   fn parse_uncon_test() {
     let line: &str = "  BGE $4 $31 @ENDLOOP";
@@ -228,6 +1131,14 @@ mod parse_test {
     assert_eq!(x, Instructions::BGE(4, 31, String::from("@ENDLOOP")));
   }
 
+  #[test]
+  fn parse_unsigned_cond_test() {
+    let x = parse_instruction("BLTU $1 $2 @END").unwrap();
+    assert_eq!(x, Instructions::BLTU(1, 2, String::from("@END")));
+    let x = parse_instruction("BGEU $3 $4 @END").unwrap();
+    assert_eq!(x, Instructions::BGEU(3, 4, String::from("@END")));
+  }
+
   #[test]
   fn parse_print_test() {
     let line: &str = "  PRINT $4";
@@ -261,5 +1172,183 @@ mod parse_test {
     let res1 = parse_instruction(line1).expect("error parsing");
     assert_eq!(res0, Instructions::PUSH(3));
     assert_eq!(res1, Instructions::POP(4));
+    let res2 = parse_instruction("SP $5").expect("error parsing");
+    assert_eq!(res2, Instructions::SP(5));
+  }
+
+  #[test]
+  fn parse_trailing_operand_test() {
+    let err = parse_instruction("PRINT $1 junk more").unwrap_err();
+    assert_eq!(err, Error::UnexpectedToken(String::from("junk")));
+    assert_eq!(
+      err.to_string(),
+      "unexpected token `junk` after the operands"
+    );
+    assert_eq!(
+      parse_instruction("EXIT now"),
+      Err(Error::UnexpectedToken(String::from("now")))
+    );
+    assert_eq!(
+      parse_instruction("LI $1 2 + 3 $4"),
+      Err(Error::UnexpectedToken(String::from("$4")))
+    );
+    assert_eq!(
+      parse_instruction("PRINT junk"),
+      Err(Error::InvalidParameter)
+    );
+  }
+
+  #[test]
+  fn preprocess_crlf_test() {
+    let mut simul = Simulator::new();
+    let lines: Vec<String> = vec![
+      String::from("@MAIN\r"),
+      String::from("// comment\r"),
+      String::from("\r"),
+      String::from("JUMP @MAIN\r"),
+    ];
+    let preprocess = preprocess_lines(&lines);
+    assert_eq!(
+      preprocess,
+      vec![String::from("@MAIN"), String::from("JUMP @MAIN")]
+    );
+    process_lines(&preprocess, &mut simul).expect("error found");
+    assert_eq!(simul.labels["@MAIN"], 0);
+    assert_eq!(
+      simul.instructions[1],
+      Instructions::JUMP(String::from("@MAIN"))
+    );
+  }
+
+  #[test]
+  fn label_same_line_test() {
+    let mut simul = Simulator::new();
+    let lines: Vec<String> = vec![String::from("LI $1 1"), String::from("  @L: ADD $1 $2 $3")];
+    process_lines(&lines, &mut simul).expect("error found");
+    assert_eq!(simul.labels["@L"], 1);
+    assert_eq!(simul.instructions[1], Instructions::LABEL);
+    assert_eq!(simul.instructions[2], Instructions::ADD(1, 2, 3));
+  }
+
+  #[test]
+  fn jump_to_same_line_label_test() {
+    let lines: Vec<String> = "@MAIN\nLI $2 3\nJUMP @L\nLI $9 9\n@L: ADDI $1 $1 1\nPRINT $1"
+      .lines()
+      .map(String::from)
+      .collect();
+    let mut simul = Simulator::new().with_captured_output();
+    simul.load(&lines).unwrap();
+    simul.run(false).unwrap();
+    assert_eq!(simul.take_output(), "PRINT: $1: 1\n");
+    assert_eq!(simul.int_registers[9], 0);
+  }
+
+  #[test]
+  fn label_colon_permissive_test() {
+    let mut simul = Simulator::new();
+    let lines: Vec<String> = vec![String::from("@MAIN:"), String::from("@END")];
+    process_lines(&lines, &mut simul).expect("error found");
+    assert_eq!(simul.labels["@MAIN"], 0);
+    assert_eq!(simul.labels["@END"], 1);
+  }
+
+  #[test]
+  fn label_colon_strict_test() {
+    let mut simul = Simulator::new().with_label_colons(true);
+    let lines: Vec<String> = vec![String::from("@MAIN:"), String::from("LI $1 1")];
+    process_lines(&lines, &mut simul).expect("error found");
+    assert_eq!(simul.labels["@MAIN"], 0);
+
+    let mut simul = Simulator::new().with_label_colons(true);
+    let lines: Vec<String> = vec![String::from("@MAIN")];
+    assert_eq!(
+      process_lines(&lines, &mut simul),
+      Err(Error::MissingLabelColon)
+    );
+  }
+
+  #[test]
+  fn inline_comment_test() {
+    let mut simul = Simulator::new();
+    let lines: Vec<String> = vec![
+      String::from("@LOOP // start of the loop"),
+      String::from("ADD $1 $1 $2 //increment"),
+      String::from("PRINT $1"),
+    ];
+    process_lines(&lines, &mut simul).expect("error found");
+    assert_eq!(simul.labels["@LOOP"], 0);
+    assert_eq!(simul.instructions[1], Instructions::ADD(1, 1, 2));
+    assert_eq!(simul.comment(0), Some("start of the loop"));
+    assert_eq!(simul.comment(1), Some("increment"));
+    assert_eq!(simul.comment(2), None);
+  }
+
+  #[test]
+  fn org_directive_test() {
+    let mut simul = Simulator::new();
+    let lines: Vec<String> = vec![
+      String::from("LI $1 1"),
+      String::from(".org 10"),
+      String::from("@TARGET"),
+      String::from("PRINT $1"),
+    ];
+    process_lines(&lines, &mut simul).expect("error found");
+    assert_eq!(simul.labels["@TARGET"], 10);
+    assert_eq!(simul.instructions[5], Instructions::SKIP);
+    assert_eq!(simul.instructions[11], Instructions::PRINT(1));
+  }
+
+  #[test]
+  fn org_too_large_test() {
+    let mut simul = Simulator::new();
+    let lines: Vec<String> = vec![String::from(".org 100000000000000")];
+    assert_eq!(
+      process_lines(&lines, &mut simul),
+      Err(Error::InvalidParameter)
+    );
+    assert!(simul.instructions.is_empty());
+  }
+
+  #[test]
+  fn org_backward_test() {
+    let mut simul = Simulator::new();
+    let lines: Vec<String> = vec![
+      String::from("LI $1 1"),
+      String::from("LI $2 2"),
+      String::from(".org 1"),
+    ];
+    assert_eq!(
+      process_lines(&lines, &mut simul),
+      Err(Error::InvalidParameter)
+    );
+  }
+
+  #[test]
+  fn parse_cond_skip_test() {
+    let res0 = parse_instruction("SKIPEQ $1 $2").expect("error parsing");
+    let res1 = parse_instruction("  SKIPNE $3 $4").expect("error parsing");
+    assert_eq!(res0, Instructions::SKIPEQ(1, 2));
+    assert_eq!(res1, Instructions::SKIPNE(3, 4));
+  }
+
+  #[test]
+  fn huge_operand_test() {
+    for line in [
+      "LI $99999999999999999999999 1",
+      "LI $1 99999999999999999999999",
+      "ADDI $1 $99999999999999999999999 1",
+      "SUBI $1 $1 99999999999999999999999",
+      "VADD $1 $2 $3 99999999999999999999999",
+      "READALL $1 99999999999999999999999",
+      "INC $99999999999999999999999",
+      "SKIPEQ $1 $99999999999999999999999",
+      "POWMOD $1 $2 $3 $99999999999999999999999",
+    ] {
+      assert_eq!(
+        parse_instruction(line),
+        Err(Error::InvalidParameter),
+        "{line}"
+      );
+    }
   }
 } // mod parse_test
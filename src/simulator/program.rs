@@ -0,0 +1,298 @@
+//! Author: TheLazyFerret (https://github.com/TheLazyFerret)
+//! Copyright (c) 2025 TheLazyFerret
+//! Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//!
+//! program building related module
+
+use std::collections::BTreeMap;
+
+use crate::simulator::{Error, Instructions, Simulator, parser::normalize_label};
+
+/// Struct representing an assembled program, ready to be loaded.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Program {
+  pub instructions: Vec<Instructions>,
+  pub labels: BTreeMap<String, usize>,
+}
+
+/// Builds a program from Rust code, without going through the text parser.
+#[derive(Debug, Default)]
+pub struct ProgramBuilder {
+  program: Program,
+}
+
+impl ProgramBuilder {
+  /// Returns an empty builder.
+  pub fn new() -> Self {
+    ProgramBuilder::default()
+  }
+
+  /// Appends any instruction.
+  pub fn instruction(mut self, instruction: Instructions) -> Self {
+    self.program.instructions.push(instruction);
+    self
+  }
+
+  /// Defines a label at the current position (`MAIN` or `@MAIN`).
+  pub fn label(mut self, name: &str) -> Self {
+    let index = self.program.instructions.len();
+    self.program.labels.insert(normalize_label(name), index);
+    self.instruction(Instructions::LABEL)
+  }
+
+  /// Appends `LI $reg imm`.
+  pub fn li(self, reg: usize, imm: i32) -> Self {
+    self.instruction(Instructions::LI(reg, imm))
+  }
+
+  /// Appends `MOVE $a $b` (`move` is a keyword).
+  pub fn mov(self, a: usize, b: usize) -> Self {
+    self.instruction(Instructions::MOVE(a, b))
+  }
+
+  /// Appends `ADD $a $b $c`.
+  pub fn add(self, a: usize, b: usize, c: usize) -> Self {
+    self.instruction(Instructions::ADD(a, b, c))
+  }
+
+  /// Appends `SUB $a $b $c`.
+  pub fn sub(self, a: usize, b: usize, c: usize) -> Self {
+    self.instruction(Instructions::SUB(a, b, c))
+  }
+
+  /// Appends `MUL $a $b $c`.
+  pub fn mul(self, a: usize, b: usize, c: usize) -> Self {
+    self.instruction(Instructions::MUL(a, b, c))
+  }
+
+  /// Appends `DIV $a $b $c`.
+  pub fn div(self, a: usize, b: usize, c: usize) -> Self {
+    self.instruction(Instructions::DIV(a, b, c))
+  }
+
+  /// Appends `REM $a $b $c`.
+  pub fn rem(self, a: usize, b: usize, c: usize) -> Self {
+    self.instruction(Instructions::REM(a, b, c))
+  }
+
+  /// Appends `PRINT $reg`.
+  pub fn print(self, reg: usize) -> Self {
+    self.instruction(Instructions::PRINT(reg))
+  }
+
+  /// Appends `PUSH $reg`.
+  pub fn push(self, reg: usize) -> Self {
+    self.instruction(Instructions::PUSH(reg))
+  }
+
+  /// Appends `POP $reg`.
+  pub fn pop(self, reg: usize) -> Self {
+    self.instruction(Instructions::POP(reg))
+  }
+
+  /// Appends `JUMP` to the label (`END` or `@END`).
+  pub fn jump(self, label: &str) -> Self {
+    self.instruction(Instructions::JUMP(normalize_label(label)))
+  }
+
+  /// Appends `BEQ $a $b` to the label (`END` or `@END`).
+  pub fn beq(self, a: usize, b: usize, label: &str) -> Self {
+    self.instruction(Instructions::BEQ(a, b, normalize_label(label)))
+  }
+
+  /// Appends `BNE $a $b` to the label (`END` or `@END`).
+  pub fn bne(self, a: usize, b: usize, label: &str) -> Self {
+    self.instruction(Instructions::BNE(a, b, normalize_label(label)))
+  }
+
+  /// Appends `BLT $a $b` to the label (`END` or `@END`).
+  pub fn blt(self, a: usize, b: usize, label: &str) -> Self {
+    self.instruction(Instructions::BLT(a, b, normalize_label(label)))
+  }
+
+  /// Appends `BLE $a $b` to the label (`END` or `@END`).
+  pub fn ble(self, a: usize, b: usize, label: &str) -> Self {
+    self.instruction(Instructions::BLE(a, b, normalize_label(label)))
+  }
+
+  /// Appends `BGT $a $b` to the label (`END` or `@END`).
+  pub fn bgt(self, a: usize, b: usize, label: &str) -> Self {
+    self.instruction(Instructions::BGT(a, b, normalize_label(label)))
+  }
+
+  /// Appends `BGE $a $b` to the label (`END` or `@END`).
+  pub fn bge(self, a: usize, b: usize, label: &str) -> Self {
+    self.instruction(Instructions::BGE(a, b, normalize_label(label)))
+  }
+
+  /// Appends `EXIT`.
+  pub fn exit(self) -> Self {
+    self.instruction(Instructions::EXIT)
+  }
+
+  /// Returns the built program, ready for `Simulator::load_program` or `Simulator::link`.
+  pub fn build(self) -> Program {
+    self.program
+  }
+} // impl ProgramBuilder
+
+impl Simulator {
+  /// Loads an already assembled program, replacing the current one
+  /// and dropping the comments and expected output of the previous source.
+  /// It goes through the same checks as `load`.
+  pub fn load_program(&mut self, program: Program) -> Result<(), Error> {
+    self.instructions = program.instructions;
+    self.labels = program.labels;
+    self.comments.clear();
+    self.expected_output.clear();
+    self.after_load()
+  }
+
+  /// Concatenates the programs in order, moving their labels to the new positions.
+  /// A label starting with an underscore (`@_LOOP`) is local: it is renamed with the position of its program
+  /// (`@_LOOP_1`), in its definition and in the jumps of that program, so programs may share it.
+  /// Any other label is global, so defining it in more than one program is an `Error::DuplicateLabel`.
+  pub fn link(programs: &[Program]) -> Result<Program, Error> {
+    let mut linked = Program::default();
+    for (module, program) in programs.iter().enumerate() {
+      let rename = |label: &str| match label.starts_with("@_") {
+        true => format!("{label}_{module}"),
+        false => label.to_owned(),
+      };
+      let offset = linked.instructions.len();
+      for (label, index) in &program.labels {
+        if linked
+          .labels
+          .insert(rename(label), index + offset)
+          .is_some()
+        {
+          return Err(Error::DuplicateLabel);
+        }
+      }
+      for instruction in &program.instructions {
+        let mut instruction = instruction.clone();
+        if let Some(target) = instruction.target_mut() {
+          *target = rename(target);
+        }
+        linked.instructions.push(instruction);
+      }
+    }
+    Ok(linked)
+  }
+}
+
+#[cfg(test)]
+mod program_test {
+  use crate::simulator::{Error, Instructions, Simulator, program::ProgramBuilder};
+
+  #[test]
+  fn builder_loop_test() {
+    let program = ProgramBuilder::new()
+      .label("MAIN")
+      .li(1, 0)
+      .li(2, 5)
+      .li(3, 1)
+      .label("LOOP")
+      .add(1, 1, 3)
+      .blt(1, 2, "LOOP")
+      .build();
+    assert_eq!(program.labels["@LOOP"], 4);
+    assert_eq!(
+      program.instructions[6],
+      Instructions::BLT(1, 2, String::from("@LOOP"))
+    );
+
+    let mut sim = Simulator::new();
+    sim.load_program(program).unwrap();
+    sim.run(false).unwrap();
+    assert_eq!(sim.int_registers[1], 5);
+  }
+
+  #[test]
+  fn link_test() {
+    let main = ProgramBuilder::new()
+      .label("MAIN")
+      .li(1, 21)
+      .jump("DOUBLE")
+      .build();
+    let double = ProgramBuilder::new().label("DOUBLE").add(1, 1, 1).build();
+    let program = Simulator::link(&[main.clone(), double]).unwrap();
+    assert_eq!(program.labels["@MAIN"], 0);
+    assert_eq!(program.labels["@DOUBLE"], 3);
+    assert_eq!(program.instructions.len(), 5);
+
+    let mut sim = Simulator::new();
+    sim.load_program(program).unwrap();
+    sim.run(false).unwrap();
+    assert_eq!(sim.int_registers[1], 42);
+
+    assert_eq!(
+      Simulator::link(&[main.clone(), main]),
+      Err(Error::DuplicateLabel)
+    );
+  }
+
+  #[test]
+  fn link_local_label_test() {
+    // Both programs count to 3 with their own `@_LOOP`.
+    let count = |builder: ProgramBuilder, reg: usize| {
+      builder
+        .li(reg, 0)
+        .li(10, 3)
+        .li(11, 1)
+        .label("_LOOP")
+        .add(reg, reg, 11)
+        .blt(reg, 10, "_LOOP")
+        .build()
+    };
+    let main = count(ProgramBuilder::new().label("MAIN"), 1);
+    let other = count(ProgramBuilder::new(), 2);
+    let program = Simulator::link(&[main, other]).unwrap();
+    assert_eq!(program.labels["@_LOOP_0"], 4);
+    assert_eq!(program.labels["@_LOOP_1"], 10);
+    assert_eq!(
+      program.instructions[12],
+      Instructions::BLT(2, 10, String::from("@_LOOP_1"))
+    );
+
+    let mut sim = Simulator::new();
+    sim.load_program(program).unwrap();
+    sim.run(false).unwrap();
+    assert_eq!((sim.int_registers[1], sim.int_registers[2]), (3, 3));
+  }
+
+  #[test]
+  fn load_program_replaces_source_test() {
+    let source: Vec<String> = "@MAIN\nLI $1 1 // one\n//! OUTPUT: PRINT: $1: 1\nPRINT $1"
+      .lines()
+      .map(String::from)
+      .collect();
+    let mut sim = Simulator::new();
+    sim.load(&source).unwrap();
+    assert_eq!(sim.comment(1), Some("one"));
+    sim
+      .load_program(ProgramBuilder::new().label("MAIN").li(1, 2).build())
+      .unwrap();
+    assert_eq!(sim.comment(1), None);
+    assert_eq!(sim.expected_output(), "");
+  }
+
+  #[test]
+  fn load_program_checks_test() {
+    let mut program = ProgramBuilder::new().label("MAIN").li(1, 1).build();
+    program.labels.insert(String::from("@END"), 7);
+    let mut sim = Simulator::new();
+    assert_eq!(
+      sim.load_program(program),
+      Err(Error::LabelOutOfRange { index: 7 })
+    );
+
+    let program = ProgramBuilder::new()
+      .label("MAIN")
+      .label("UNUSED")
+      .li(1, 1)
+      .build();
+    let mut sim = Simulator::new().with_require_label_use(true);
+    assert_eq!(sim.load_program(program), Err(Error::UnusedLabel));
+  }
+} // mod program_test
@@ -6,7 +6,7 @@
 
 use std::collections::LinkedList;
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct Stack<T> {
   list: LinkedList<T>,
 }
@@ -25,4 +25,12 @@ impl<T> Stack<T> {
   pub fn pop(&mut self) -> Option<T> {
     self.list.pop_front()
   }
+
+  pub fn len(&self) -> usize {
+    self.list.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.list.is_empty()
+  }
 }
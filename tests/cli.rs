@@ -0,0 +1,29 @@
+//! Author: TheLazyFerret (https://github.com/TheLazyFerret)
+//! Copyright (c) 2025 TheLazyFerret
+//! Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//!
+//! Runs the binary and checks what it writes to stdout and to stderr
+
+use std::{fs, path::Path, process::Command};
+
+#[test]
+fn debug_trace_on_stderr_test() {
+  let example = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/arithmetic.asm");
+  let output = Command::new(env!("CARGO_BIN_EXE_interpreter-rs"))
+    .arg("--debug")
+    .arg(&example)
+    .output()
+    .expect("error running the binary");
+  assert!(output.status.success());
+
+  let stdout = String::from_utf8(output.stdout).unwrap();
+  let expected = fs::read_to_string(example.with_extension("out")).unwrap();
+  assert_eq!(stdout, expected);
+
+  let stderr = String::from_utf8(output.stderr).unwrap();
+  let lines: Vec<&str> = stderr.lines().collect();
+  assert_eq!(lines[..2], ["Preprocess... Done", "Parsing... Done"]);
+  assert_eq!(lines[2..5], ["LABEL", "LI $1 17", "LI $2 5"]);
+  assert!(lines.iter().all(|x| !x.starts_with("PRINT:")));
+  assert_eq!(lines.last(), Some(&"PRINT $3"));
+}
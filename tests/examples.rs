@@ -0,0 +1,37 @@
+//! Author: TheLazyFerret (https://github.com/TheLazyFerret)
+//! Copyright (c) 2025 TheLazyFerret
+//! Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//!
+//! Runs every program in `examples/` and compares its output with the `.out` file next to it
+
+use std::{
+  fs::{self, File},
+  path::{Path, PathBuf},
+};
+
+use interpreter_rs::simulator::Simulator;
+
+/// Loads and runs the program, returning everything it printed.
+fn run(path: &Path) -> String {
+  let mut sim = Simulator::new().with_captured_output();
+  let file = File::open(path).expect("error opening the example");
+  sim.load_reader(file).expect("error loading the example");
+  sim.run(false).expect("error running the example");
+  sim.take_output()
+}
+
+#[test]
+fn examples_test() {
+  let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples");
+  let mut programs: Vec<PathBuf> = fs::read_dir(dir)
+    .expect("error reading examples/")
+    .map(|x| x.unwrap().path())
+    .filter(|x| x.extension().is_some_and(|x| x == "asm"))
+    .collect();
+  programs.sort();
+  assert!(!programs.is_empty());
+  for program in programs {
+    let expected = fs::read_to_string(program.with_extension("out")).expect("missing .out file");
+    assert_eq!(run(&program), expected, "output of {}", program.display());
+  }
+}